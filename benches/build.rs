@@ -0,0 +1,50 @@
+mod common;
+
+use std::time::Instant;
+
+use common::dictionary;
+use datrie::Dat;
+
+fn main() {
+    let size = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let words = dictionary(size);
+
+    let start = Instant::now();
+    let mut dat = Dat::new();
+    dat.load(
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w.as_str(), i))
+            .collect(),
+    );
+    let elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let found = words.iter().filter(|w| dat.contain(w)).count();
+    println!(
+        "build {} keys: {:?}, lookup: {:?} ({} found)",
+        size,
+        elapsed,
+        start.elapsed(),
+        found
+    );
+
+    let mut sorted: Vec<(&str, usize)> = words.iter().map(|w| w.as_str()).zip(0..).collect();
+    sorted.sort_by_key(|&(w, _)| w);
+    sorted.dedup_by_key(|&mut (w, _)| w);
+
+    let start = Instant::now();
+    let dat = Dat::build_sorted(sorted.clone());
+    let elapsed = start.elapsed();
+    let found = sorted.iter().filter(|(w, _)| dat.contain(w)).count();
+    println!(
+        "build_sorted {} keys: {:?} ({} found)",
+        sorted.len(),
+        elapsed,
+        found
+    );
+}
@@ -0,0 +1,20 @@
+/// Generates `size` pseudo-random words of 2 to 5 CJK chars, the same on
+/// every run.
+pub fn dictionary(size: usize) -> Vec<String> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+
+    (0..size)
+        .map(|_| {
+            let len = 2 + (next() % 4) as usize;
+            (0..len)
+                .map(|_| char::from_u32(0x4e00 + (next() % 3000) as u32).unwrap())
+                .collect()
+        })
+        .collect()
+}
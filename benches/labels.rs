@@ -0,0 +1,65 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::dictionary;
+use datrie::Dat;
+
+fn time<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let start = Instant::now();
+    let count = f();
+    (start.elapsed(), count)
+}
+
+fn main() {
+    let size = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let words = dictionary(size);
+    let text: String = words.concat();
+
+    let mut chars: Dat<usize> = Dat::new();
+    let (char_build, _) = time(|| {
+        for (i, word) in words.iter().enumerate() {
+            chars.append(word, i);
+        }
+        0
+    });
+    let mut bytes: Dat<usize, u8> = Dat::new();
+    let (byte_build, _) = time(|| {
+        for (i, word) in words.iter().enumerate() {
+            bytes.append(word.as_bytes(), i);
+        }
+        0
+    });
+
+    let (char_lookup, char_found) = time(|| words.iter().filter(|w| chars.contain(w)).count());
+    let (byte_lookup, byte_found) =
+        time(|| words.iter().filter(|w| bytes.contain(w.as_bytes())).count());
+
+    let (char_scan, char_hits) = time(|| {
+        let mut hits = 0;
+        for (start, _) in text.char_indices() {
+            hits += chars.search(&text[start..]).len();
+        }
+        hits
+    });
+    let (byte_scan, byte_hits) = time(|| {
+        let mut hits = 0;
+        for (start, _) in text.char_indices() {
+            hits += bytes.search(&text.as_bytes()[start..]).len();
+        }
+        hits
+    });
+
+    println!("{} keys, {} bytes of text", size, text.len());
+    println!(
+        "char: build {:?}, lookup {:?} ({} found), search {:?} ({} hits)",
+        char_build, char_lookup, char_found, char_scan, char_hits
+    );
+    println!(
+        "byte: build {:?}, lookup {:?} ({} found), search {:?} ({} hits)",
+        byte_build, byte_lookup, byte_found, byte_scan, byte_hits
+    );
+}
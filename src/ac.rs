@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+
+use crate::censor::ScanChars;
+use crate::double_array::DoubleArray;
+use crate::normalize::Normalized;
+use crate::{Dat, TERMINAL};
+
+/// Aho-Corasick automaton over a [`Dat`], finding every occurrence of every
+/// key in a single pass over the text.
+///
+/// Failure and output links are stored per state, parallel to `base` and
+/// `check`, so the goto function is still the double array transition.
+pub struct AhoCorasick<T> {
+    dat: Dat<T>,
+    fail: Vec<usize>,
+    output: Vec<usize>,
+    depth: Vec<usize>,
+    max_depth: usize,
+}
+
+impl<T> AhoCorasick<T> {
+    pub fn new(dat: Dat<T>) -> Self {
+        let len = dat.base.len();
+        let mut fail = vec![0; len];
+        let mut output = vec![0; len];
+        let mut depth = vec![0; len];
+        let mut queue = VecDeque::from([0]);
+
+        while let Some(state) = queue.pop_front() {
+            for code in dat.children(state) {
+                if code == TERMINAL {
+                    continue;
+                }
+                let child = dat.child_base(state) + code;
+                depth[child] = depth[state] + 1;
+
+                if state != 0 {
+                    let mut target = fail[state];
+                    fail[child] = loop {
+                        if let Some(next) = dat.transition(target, code) {
+                            break next;
+                        }
+                        if target == 0 {
+                            break 0;
+                        }
+                        target = fail[target];
+                    };
+                    output[child] = if fail[child] != 0 && dat.terminal(fail[child]).is_some() {
+                        fail[child]
+                    } else {
+                        output[fail[child]]
+                    };
+                }
+                queue.push_back(child);
+            }
+        }
+
+        Self {
+            max_depth: depth.iter().copied().max().unwrap_or(0),
+            dat,
+            fail,
+            output,
+            depth,
+        }
+    }
+
+    /// Returns an iterator over all matches in `text` as `(start, end, value)`
+    /// byte ranges, ordered by end position and then from longest to shortest.
+    ///
+    /// With a normalizer on the trie, matches are found in the normalized
+    /// text, and only those covering whole chars of `text` are reported.
+    /// Ignorable chars are skipped as in [`Dat::find_all`].
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> FindIter<'a, T> {
+        let chars = ScanChars::new(text, self.dat.ignorable);
+        FindIter {
+            ac: self,
+            text,
+            labels: Normalized::new(chars, self.dat.normalizer),
+            state: 0,
+            end: None,
+            pending: 0,
+            pos: 0,
+            boundary: Some(0),
+            starts: vec![None; self.max_depth + 1],
+        }
+    }
+
+    fn next_state(&self, mut state: usize, ch: char) -> usize {
+        let Some(code) = self.dat.code(ch) else {
+            return 0;
+        };
+        loop {
+            if let Some(next) = self.dat.transition(state, code) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+}
+
+impl<T> From<Dat<T>> for AhoCorasick<T> {
+    fn from(dat: Dat<T>) -> Self {
+        Self::new(dat)
+    }
+}
+
+/// Iterator over matches, created by [`AhoCorasick::find_iter`].
+pub struct FindIter<'a, T> {
+    ac: &'a AhoCorasick<T>,
+    text: &'a str,
+    labels: Normalized<ScanChars<'a>, char>,
+    state: usize,
+    end: Option<usize>,
+    pending: usize,
+    // Labels read so far, and the start offset of the char each of the last
+    // `max_depth + 1` came from if it was the first label of that char.
+    pos: usize,
+    boundary: Option<usize>,
+    starts: Vec<Option<usize>>,
+}
+
+impl<'a, T> Iterator for FindIter<'a, T> {
+    type Item = (usize, usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.pending != 0 {
+                let state = self.pending;
+                self.pending = self.ac.output[state];
+                let first = (self.pos - self.ac.depth[state]) % self.starts.len();
+                if let (Some(end), Some(start), Some(value)) =
+                    (self.end, self.starts[first], self.ac.dat.value(state))
+                {
+                    let start = self.ac.dat.skip_ignorable(self.text, start);
+                    return Some((start, end, value));
+                }
+            }
+
+            let (end, ch) = self.labels.next()?;
+            let slot = self.pos % self.starts.len();
+            self.starts[slot] = self.boundary;
+            self.pos += 1;
+            self.boundary = end;
+            self.state = self.ac.next_state(self.state, ch);
+            self.end = end;
+            self.pending = self.state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_iter() {
+        let mut dat = Dat::new();
+        dat.load(vec![("he", 1), ("she", 2), ("his", 3), ("hers", 4)]);
+        let ac = AhoCorasick::new(dat);
+
+        assert_eq!(
+            ac.find_iter("ushers").collect::<Vec<_>>(),
+            vec![(1, 4, &2), (2, 4, &1), (2, 6, &4)]
+        );
+        assert_eq!(
+            ac.find_iter("ahishe").collect::<Vec<_>>(),
+            vec![(1, 4, &3), (3, 6, &2), (4, 6, &1)]
+        );
+        assert_eq!(ac.find_iter("xyz").count(), 0);
+    }
+
+    #[test]
+    fn test_find_iter_matches_search() {
+        let mut dat = Dat::new();
+        dat.load(vec![
+            ("我", 1),
+            ("我喜欢", 2),
+            ("喜欢", 3),
+            ("你不配", 4),
+            ("不配", 5),
+        ]);
+        let text = "我喜欢你，你不配！我不喜欢";
+
+        let mut expected = Vec::new();
+        for (start, _) in text.char_indices() {
+            for (key, &value) in dat.search(&text[start..]) {
+                expected.push((start, start + key.len(), value));
+            }
+        }
+        expected.sort();
+
+        let ac = AhoCorasick::from(dat);
+        let mut found: Vec<_> = ac
+            .find_iter(text)
+            .map(|(start, end, &value)| (start, end, value))
+            .collect();
+        found.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_find_iter_normalized() {
+        let mut dat = Dat::with_normalizer(crate::normalize::lowercase);
+        dat.load(vec![("he", 1), ("she", 2), ("i", 3)]);
+        let ac = AhoCorasick::new(dat);
+
+        assert_eq!(
+            ac.find_iter("uSHE").collect::<Vec<_>>(),
+            vec![(1, 4, &2), (2, 4, &1)]
+        );
+        // "İ" lowercases to "i" plus a combining dot, so "i" would end inside
+        // the char and is not reported.
+        assert_eq!(ac.find_iter("İ, I").collect::<Vec<_>>(), vec![(4, 5, &3)]);
+    }
+
+    #[test]
+    fn test_find_iter_ignorable() {
+        let mut dat = Dat::new();
+        dat.load(vec![("he", 1), ("she", 2), ("你不配", 3)]);
+        dat.set_ignorable(Some(crate::normalize::noise));
+        let ac = AhoCorasick::new(dat);
+
+        assert_eq!(
+            ac.find_iter("u s-h e").collect::<Vec<_>>(),
+            vec![(2, 7, &2), (4, 7, &1)]
+        );
+        assert_eq!(
+            ac.find_iter("说：你，不\u{200b}配").collect::<Vec<_>>(),
+            vec![(6, 21, &3)]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::io::{self, Read, Write};
+
+use crate::double_array::DoubleArray;
+use crate::{Dat, DatView, Label};
+
+pub(crate) const MAGIC: &[u8; 4] = b"DATR";
+pub(crate) const VERSION: u32 = 3;
+
+/// Endian-stable binary encoding of values stored in a [`Dat`], used by
+/// [`Dat::write_to`] and [`Dat::read_from`].
+pub trait Codec: Sized {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_codec_num {
+    ($($ty:ty),*) => {
+        $(
+            impl Codec for $ty {
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    let mut buf = [0; std::mem::size_of::<$ty>()];
+                    reader.read_exact(&mut buf)?;
+                    Ok(<$ty>::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_num!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Codec for usize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        usize::try_from(u64::decode(reader)?).map_err(|_| invalid("usize out of range"))
+    }
+}
+
+impl Codec for isize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as i64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        isize::try_from(i64::decode(reader)?).map_err(|_| invalid("isize out of range"))
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("invalid bool")),
+        }
+    }
+}
+
+impl Codec for char {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid("invalid char"))
+    }
+}
+
+impl Codec for () {
+    fn encode<W: Write>(&self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn decode<R: Read>(_reader: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = usize::decode(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid("invalid utf-8"))
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().encode(writer)?;
+        self.iter().try_for_each(|item| item.encode(writer))
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = usize::decode(reader)?;
+        (0..len).map(|_| T::decode(reader)).collect()
+    }
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<T: Codec, L: Label> Dat<T, L> {
+    /// Writes the trie in a versioned little-endian binary format.
+    ///
+    /// The layout is the magic `DATR`, a `u32` version, then the slot count
+    /// and the `base`, `check`, `child` and `sibling` arrays, the free list
+    /// head, the labels in code order, the two-level code table as its index
+    /// and blocks, the `(label, code)` pairs of labels outside the table
+    /// sorted by label, the free tail keys and finally the tail as a table of
+    /// `(key, offset)` pairs sorted by key followed by the encoded values.
+    /// Labels and codes are stored as `u32`; counts and offsets are `u64`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        VERSION.encode(&mut writer)?;
+
+        self.base.len().encode(&mut writer)?;
+        for &base in &self.base {
+            base.encode(&mut writer)?;
+        }
+        for &check in &self.check {
+            check.encode(&mut writer)?;
+        }
+        for &child in &self.child {
+            (child as u32).encode(&mut writer)?;
+        }
+        for &sibling in &self.sibling {
+            (sibling as u32).encode(&mut writer)?;
+        }
+        self.free_head.encode(&mut writer)?;
+
+        self.labels.len().encode(&mut writer)?;
+        for label in &self.labels {
+            label.into_u32().encode(&mut writer)?;
+        }
+        self.code_map.index.len().encode(&mut writer)?;
+        for block in &self.code_map.index {
+            block.encode(&mut writer)?;
+        }
+        self.code_map.table.len().encode(&mut writer)?;
+        for code in &self.code_map.table {
+            code.encode(&mut writer)?;
+        }
+        let mut fallback: Vec<(u32, u32)> = self
+            .code_map
+            .fallback
+            .iter()
+            .map(|(&label, &code)| (label, code))
+            .collect();
+        fallback.sort_unstable();
+        fallback.len().encode(&mut writer)?;
+        for (label, code) in fallback {
+            label.encode(&mut writer)?;
+            code.encode(&mut writer)?;
+        }
+
+        self.tail_free.len().encode(&mut writer)?;
+        for tail_key in &self.tail_free {
+            tail_key.encode(&mut writer)?;
+        }
+
+        let mut keys: Vec<i32> = self.tail.keys().copied().collect();
+        keys.sort_unstable();
+        let mut values = Vec::new();
+        keys.len().encode(&mut writer)?;
+        for key in keys {
+            key.encode(&mut writer)?;
+            values.len().encode(&mut writer)?;
+            self.tail[&key].encode(&mut values)?;
+        }
+        values.len().encode(&mut writer)?;
+        writer.write_all(&values)?;
+
+        writer.flush()
+    }
+
+    /// Reads a trie written by [`Dat::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let dat = DatView::new(&bytes)?.to_dat()?;
+        if dat.is_valid() {
+            Ok(dat)
+        } else {
+            Err(invalid("corrupted datrie data"))
+        }
+    }
+}
+
+impl<T, L: Label> Dat<T, L> {
+    pub(crate) fn is_valid(&self) -> bool {
+        let len = self.base.len();
+        let in_range = |index: i32| (index.unsigned_abs() as usize) < len;
+        if len == 0
+            || self.check[0] != 0
+            || !self
+                .labels
+                .iter()
+                .zip(1..)
+                .all(|(&label, code)| self.code(label) == Some(code))
+            || !self
+                .code_map
+                .iter()
+                .all(|(_, code)| code <= self.labels.len())
+            || (self.free_head != 0 && (self.free_head >= len || !self.is_vacant(self.free_head)))
+        {
+            return false;
+        }
+
+        (0..len).all(|state| {
+            // Each vacant slot must be the previous slot of its next one.
+            // The vacant slots then split into cycles, so walks from
+            // `free_head` in `find_base` and relinks in `unlink_vacant` stay
+            // among vacant slots and come back to where they started.
+            if self.is_vacant(state) {
+                let next = self.check[state].unsigned_abs() as usize;
+                return in_range(self.base[state])
+                    && next < len
+                    && self.is_vacant(next)
+                    && self.base[next] == -(state as i32);
+            }
+            if !in_range(self.check[state]) || self.is_vacant(self.check[state] as usize) {
+                return false;
+            }
+            if self.base[state] < 0 {
+                return self.child[state] == 0 && self.tail.contains_key(&self.base[state]);
+            }
+
+            let base = self.child_base(state);
+            let mut code = self.child[state];
+            for _ in 0..=self.alphabet_len() {
+                if code == 0 {
+                    return true;
+                }
+                if code > self.alphabet_len() || self.transition(state, code).is_none() {
+                    return false;
+                }
+                code = self.sibling[base + code];
+            }
+            false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_read() {
+        let mut dat = Dat::new();
+        dat.load(vec![
+            ("我", 1u64),
+            ("我喜欢", 2),
+            ("我喜欢你", 3),
+            ("你不配", 4),
+            ("key", 5),
+        ]);
+        dat.remove("key");
+
+        let mut buf = Vec::new();
+        dat.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..4], b"DATR");
+
+        let mut loaded: Dat<u64> = Dat::read_from(buf.as_slice()).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.search("我喜欢你").len(), 3);
+
+        loaded.append("key", 6);
+        loaded.append("你", 7);
+        assert_eq!(loaded.lookup("key"), Some(&6));
+        assert_eq!(loaded.lookup("你不配"), Some(&4));
+        assert_eq!(loaded.remove("我"), Some(1));
+        assert_eq!(loaded.lookup("我喜欢"), Some(&2));
+    }
+
+    #[test]
+    fn test_write_read_owned() {
+        let mut dat = Dat::new();
+        dat.append("喜欢", vec!["v".to_string(), "n".to_string()]);
+        dat.append("喜", vec![]);
+
+        let mut buf = Vec::new();
+        dat.write_to(&mut buf).unwrap();
+        let loaded: Dat<Vec<String>> = Dat::read_from(buf.as_slice()).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_write_read_u16() {
+        let mut dat: Dat<u8, u16> = Dat::new();
+        dat.load(vec![(&[300, 2][..], 1), (&[300], 2), (&[7], 3)]);
+
+        let mut buf = Vec::new();
+        dat.write_to(&mut buf).unwrap();
+        let loaded: Dat<u8, u16> = Dat::read_from(buf.as_slice()).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+
+        let view: DatView<u8, u16> = DatView::new(&buf).unwrap();
+        assert_eq!(view.lookup(&[300, 2]), Some(1));
+        assert_eq!(
+            view.search(&[300, 2]),
+            vec![(vec![300], 2), (vec![300, 2], 1)]
+        );
+        assert!(Dat::<u8, u8>::read_from(buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        let mut dat = Dat::new();
+        dat.load(vec![("abc", 'x'), ("abd", 'y')]);
+        let mut buf = Vec::new();
+        dat.write_to(&mut buf).unwrap();
+
+        let mut bad_magic = buf.clone();
+        bad_magic[0] = b'X';
+        assert!(Dat::<char>::read_from(bad_magic.as_slice()).is_err());
+
+        let mut bad_version = buf.clone();
+        bad_version[4] = 9;
+        assert!(Dat::<char>::read_from(bad_version.as_slice()).is_err());
+
+        assert!(Dat::<char>::read_from(&buf[..buf.len() - 1]).is_err());
+
+        let mut bad_check = buf.clone();
+        let check_start = 16 + 4 * dat.base.len();
+        bad_check[check_start + 4..check_start + 8].copy_from_slice(&1000i32.to_le_bytes());
+        assert!(Dat::<char>::read_from(bad_check.as_slice()).is_err());
+
+        // A head linked to itself while its neighbour still points at it
+        // used to load, then hang the next insertion in `find_base`.
+        dat.remove("abd");
+        let head = dat.free_head;
+        assert_ne!(-dat.check[head] as usize, head);
+        let mut bad_free_list = Vec::new();
+        dat.write_to(&mut bad_free_list).unwrap();
+        let head_check = check_start + 4 * head;
+        bad_free_list[head_check..head_check + 4].copy_from_slice(&(-(head as i32)).to_le_bytes());
+        assert!(Dat::<char>::read_from(bad_free_list.as_slice()).is_err());
+
+        assert!(Dat::<char>::read_from(buf.as_slice()).is_ok());
+    }
+}
@@ -0,0 +1,254 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::double_array::DoubleArray;
+use crate::{Dat, Label, Labels, TERMINAL};
+
+impl<T, L: Label> Dat<T, L> {
+    /// Creates an empty trie whose labels get codes in the order of
+    /// `alphabet`, starting from 1. Labels missing from it are coded in
+    /// first-seen order after it. Labels with a fixed alphabet such as `u8`
+    /// ignore it.
+    pub fn with_alphabet<I: IntoIterator<Item = L>>(alphabet: I) -> Self {
+        let mut dat = Self::new();
+        for label in alphabet {
+            dat.get_code(label);
+        }
+        dat
+    }
+
+    /// Creates an empty trie where the labels of each class share one code,
+    /// as if every label were passed to [`Dat::add_equivalent`] with the
+    /// first one of its class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label is in more than one class, or if `L` has a fixed
+    /// alphabet.
+    pub fn with_equivalents<I, C>(classes: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: IntoIterator<Item = L>,
+    {
+        assert!(
+            L::ALPHABET.is_none(),
+            "labels with a fixed alphabet have no equivalents"
+        );
+        let mut dat = Self::new();
+        for class in classes {
+            let mut class = class.into_iter();
+            let Some(label) = class.next() else {
+                continue;
+            };
+            assert!(dat.code(label).is_none(), "overlapping classes");
+            dat.get_code(label);
+            for alias in class {
+                assert!(dat.add_equivalent(alias, label), "overlapping classes");
+            }
+        }
+        dat
+    }
+
+    /// Builds a trie from a list sorted by key with no duplicates.
+    ///
+    /// Nodes are laid out top-down with all children of a node placed at
+    /// once, so nothing is ever relocated and the arrays come out denser than
+    /// with `load`. Labels are coded by descending frequency across the keys,
+    /// so the most common ones get the smallest codes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys are not strictly increasing.
+    pub fn build_sorted<K: Labels<L> + ?Sized>(list: Vec<(&K, T)>) -> Self {
+        let mut counts: HashMap<L, usize> = HashMap::new();
+        for (key, _) in &list {
+            for (_, label) in key.labels() {
+                *counts.entry(label).or_default() += 1;
+            }
+        }
+        let mut alphabet: Vec<(L, usize)> = counts.into_iter().collect();
+        alphabet.sort_unstable_by_key(|&(label, count)| (Reverse(count), label));
+
+        Self::build_sorted_with_alphabet(alphabet.into_iter().map(|(label, _)| label), list)
+    }
+
+    /// Same as [`Dat::build_sorted`], with codes assigned as in
+    /// [`Dat::with_alphabet`].
+    pub fn build_sorted_with_alphabet<I, K>(alphabet: I, list: Vec<(&K, T)>) -> Self
+    where
+        I: IntoIterator<Item = L>,
+        K: Labels<L> + ?Sized,
+    {
+        let (keys, mut values): (Vec<Vec<L>>, Vec<Option<T>>) = list
+            .into_iter()
+            .map(|(key, value)| (key.labels().map(|(_, label)| label).collect(), Some(value)))
+            .unzip();
+        assert!(
+            keys.windows(2).all(|pair| pair[0] < pair[1]),
+            "keys must be sorted and unique"
+        );
+
+        let mut dat = Self::with_alphabet(alphabet);
+        if !keys.is_empty() {
+            dat.build_node(0, &keys, &mut values, 0..keys.len(), 0);
+        }
+        dat
+    }
+
+    fn build_node(
+        &mut self,
+        state: usize,
+        keys: &[Vec<L>],
+        values: &mut [Option<T>],
+        range: Range<usize>,
+        depth: usize,
+    ) {
+        let mut groups = Vec::new();
+        let mut start = range.start;
+        if keys[start].len() == depth {
+            groups.push((TERMINAL, start..start + 1));
+            start += 1;
+        }
+        while start < range.end {
+            let label = keys[start][depth];
+            let end = (start..range.end)
+                .find(|&i| keys[i][depth] != label)
+                .unwrap_or(range.end);
+            groups.push((self.get_code(label), start..end));
+            start = end;
+        }
+
+        let codes: Vec<usize> = groups.iter().map(|&(code, _)| code).collect();
+        let base = self.find_base(&codes);
+        self.base[state] = base as i32;
+
+        for (code, range) in groups.iter().rev() {
+            let new_state = base + code;
+            self.occupy(new_state);
+            self.check[new_state] = state as i32;
+            if *code == TERMINAL {
+                self.base[new_state] = self.alloc_tail(values[range.start].take().unwrap());
+            } else {
+                self.sibling[new_state] = self.child[state];
+                self.child[state] = *code;
+            }
+        }
+
+        for (code, range) in groups {
+            if code != TERMINAL {
+                self.build_node(base + code, keys, values, range, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::double_array::DoubleArray;
+    use crate::Dat;
+
+    #[test]
+    fn test_build_sorted() {
+        let dat = Dat::build_sorted(vec![
+            ("你不配", 4),
+            ("我", 1),
+            ("我喜欢", 2),
+            ("我喜欢你", 3),
+        ]);
+
+        assert_eq!(dat.lookup("我"), Some(&1));
+        assert_eq!(dat.lookup("我喜欢你"), Some(&3));
+        assert_eq!(dat.lookup("你不配"), Some(&4));
+        assert_eq!(dat.lookup("我喜"), None);
+        assert_eq!(
+            dat.search("我喜欢你"),
+            vec![
+                ("我".to_string(), &1),
+                ("我喜欢".to_string(), &2),
+                ("我喜欢你".to_string(), &3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_sorted_denser() {
+        let mut keys: Vec<String> = (0..2000).map(|i| format!("{:x}", i * 7919)).collect();
+        keys.sort();
+        let list: Vec<(&str, usize)> = keys.iter().map(|k| k.as_str()).zip(0..).collect();
+
+        let built = Dat::build_sorted(list.clone());
+        let mut loaded = Dat::new();
+        loaded.load(list);
+
+        assert!(built.base.len() <= loaded.base.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(built.lookup(key), Some(&i));
+        }
+    }
+
+    #[test]
+    fn test_build_sorted_then_append() {
+        let mut dat = Dat::build_sorted(vec![("abc", 1), ("abd", 2)]);
+        dat.append("ab", 3);
+        dat.append("b", 4);
+
+        assert_eq!(dat.lookup("abc"), Some(&1));
+        assert_eq!(dat.lookup("abd"), Some(&2));
+        assert_eq!(dat.lookup("ab"), Some(&3));
+        assert_eq!(dat.lookup("b"), Some(&4));
+        assert_eq!(dat.remove("abc"), Some(1));
+        assert_eq!(dat.lookup("abd"), Some(&2));
+    }
+
+    #[test]
+    fn test_build_sorted_empty_key() {
+        let dat = Dat::build_sorted(vec![("", 0), ("a", 1)]);
+
+        assert_eq!(dat.lookup(""), Some(&0));
+        assert_eq!(dat.lookup("a"), Some(&1));
+        assert_eq!(dat.keys().collect::<Vec<_>>(), vec!["", "a"]);
+    }
+
+    #[test]
+    fn test_build_sorted_u16() {
+        let dat: Dat<usize, u16> =
+            Dat::build_sorted(vec![(&[1, 2][..], 0), (&[1, 2, 3], 1), (&[4], 2)]);
+
+        assert_eq!(dat.lookup(&[1, 2, 3]), Some(&1));
+        assert_eq!(dat.lookup(&[1]), None);
+        assert_eq!(dat.values().collect::<Vec<_>>(), vec![&0, &1, &2]);
+    }
+
+    #[test]
+    fn test_build_sorted_frequency_codes() {
+        let dat = Dat::build_sorted(vec![("ab", 1), ("b", 2), ("cb", 3)]);
+
+        assert_eq!(dat.labels, vec!['b', 'a', 'c']);
+        assert_eq!(dat.lookup("cb"), Some(&3));
+    }
+
+    #[test]
+    fn test_with_alphabet() {
+        let mut dat = Dat::with_alphabet("欢喜我".chars());
+        dat.load(vec![("我喜欢", 1), ("你", 2)]);
+
+        assert_eq!(dat.code('欢'), Some(1));
+        assert_eq!(dat.code('我'), Some(3));
+        assert_eq!(dat.code('你'), Some(4));
+        assert_eq!(dat.lookup("我喜欢"), Some(&1));
+
+        let built = Dat::build_sorted_with_alphabet(['b', 'a'], vec![("a", 1), ("b", 2)]);
+        assert_eq!(built.labels, vec!['b', 'a']);
+        assert_eq!(built.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        let bytes: Dat<i32, u8> = Dat::with_alphabet(*b"ba");
+        assert!(bytes.labels.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_build_sorted_unsorted() {
+        Dat::build_sorted(vec![("b", 1), ("a", 2)]);
+    }
+}
@@ -0,0 +1,172 @@
+use std::str::CharIndices;
+
+use crate::double_array::DoubleArray;
+use crate::Dat;
+
+/// Chars of a scanned text without the ignorable ones, each with the offset
+/// just past it.
+pub(crate) struct ScanChars<'a> {
+    chars: CharIndices<'a>,
+    ignorable: Option<fn(char) -> bool>,
+}
+
+impl<'a> ScanChars<'a> {
+    pub(crate) fn new(text: &'a str, ignorable: Option<fn(char) -> bool>) -> Self {
+        Self {
+            chars: text.char_indices(),
+            ignorable,
+        }
+    }
+}
+
+impl Iterator for ScanChars<'_> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ignorable = self.ignorable;
+        self.chars
+            .find(|&(_, ch)| !ignorable.is_some_and(|ignorable| ignorable(ch)))
+            .map(|(i, ch)| (i + ch.len_utf8(), ch))
+    }
+}
+
+impl<T> Dat<T> {
+    /// Sets the chars skipped by text scans, such as [`Dat::find_all`] and
+    /// [`AhoCorasick`](crate::AhoCorasick), so "你 不 配" still matches
+    /// "你不配". Matches never start or end on an ignorable char, and keys
+    /// containing one are not found by scans. Like the normalizer, it is not
+    /// saved.
+    pub fn set_ignorable(&mut self, ignorable: Option<fn(char) -> bool>) {
+        self.ignorable = ignorable;
+    }
+
+    /// Finds the leftmost-longest, non-overlapping keys in `text` and returns
+    /// them as `(start, end, value)` byte ranges. Chars set with
+    /// [`Dat::set_ignorable`] are skipped inside keys.
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize, &T)> {
+        let mut hits = Vec::new();
+        let mut start = 0;
+
+        loop {
+            start = self.skip_ignorable(text, start);
+            let Some(ch) = text[start..].chars().next() else {
+                break;
+            };
+            match self.longest_match(ScanChars::new(&text[start..], self.ignorable)) {
+                Some((len, _, value)) if len > 0 => {
+                    hits.push((start, start + len, value));
+                    start += len;
+                }
+                _ => start += ch.len_utf8(),
+            }
+        }
+
+        hits
+    }
+
+    /// Returns the offset of the first char at or after `start` that is not
+    /// ignorable.
+    pub(crate) fn skip_ignorable(&self, text: &str, start: usize) -> usize {
+        let Some(ignorable) = self.ignorable else {
+            return start;
+        };
+        text[start..]
+            .char_indices()
+            .find(|&(_, ch)| !ignorable(ch))
+            .map_or(text.len(), |(i, _)| start + i)
+    }
+
+    /// Replaces every hit of [`Dat::find_all`] with the string returned by
+    /// `replace` for the matched text and its value.
+    ///
+    /// Returns the filtered text and the hits, whose ranges refer to `text`.
+    pub fn replace_all<F>(&self, text: &str, mut replace: F) -> (String, Vec<(usize, usize, &T)>)
+    where
+        F: FnMut(&str, &T) -> String,
+    {
+        let hits = self.find_all(text);
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for &(start, end, value) in &hits {
+            result.push_str(&text[last..start]);
+            result.push_str(&replace(&text[start..end], value));
+            last = end;
+        }
+        result.push_str(&text[last..]);
+
+        (result, hits)
+    }
+
+    /// Masks every char of every hit of [`Dat::find_all`] with `mask`.
+    pub fn censor(&self, text: &str, mask: char) -> (String, Vec<(usize, usize, &T)>) {
+        self.replace_all(text, |word, _| word.chars().map(|_| mask).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{normalize, Dat};
+
+    #[test]
+    fn test_find_all() {
+        let mut dat = Dat::new();
+        dat.load(vec![("你", 1), ("你不配", 2), ("不配", 3), ("配", 4)]);
+
+        assert_eq!(
+            dat.find_all("说你不配，配吗"),
+            vec![(3, 12, &2), (15, 18, &4)]
+        );
+        assert!(dat.find_all("没有").is_empty());
+    }
+
+    #[test]
+    fn test_censor() {
+        let mut dat = Dat::new();
+        dat.load(vec![("你不配", 1), ("bad", 2)]);
+
+        let (text, hits) = dat.censor("你不配, too bad!", '*');
+        assert_eq!(text, "***, too ***!");
+        assert_eq!(hits, vec![(0, 9, &1), (15, 18, &2)]);
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let mut dat = Dat::new();
+        dat.load(vec![("你不配", 1), ("bad", 2)]);
+
+        let (text, hits) = dat.replace_all("你不配, bad", |word, value| match value {
+            1 => "你很好".to_string(),
+            _ => format!("[{}]", word.len()),
+        });
+        assert_eq!(text, "你很好, [3]");
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[test]
+    fn test_censor_normalized() {
+        let mut dat = Dat::with_normalizer(normalize::fold);
+        dat.load(vec![("bad", 1), ("你不配", 2)]);
+
+        let (text, hits) = dat.censor("ＢＡＤ, Bad, 你不配", '*');
+        assert_eq!(text, "***, ***, ***");
+        assert_eq!(hits, vec![(0, 9, &1), (11, 14, &1), (16, 25, &2)]);
+    }
+
+    #[test]
+    fn test_censor_ignorable() {
+        let mut dat = Dat::new();
+        dat.load(vec![("你不配", 1), ("bad", 2)]);
+        dat.set_ignorable(Some(normalize::noise));
+
+        let (text, hits) = dat.censor("你 不 配! b.a\u{200b}d", '*');
+        assert_eq!(text, "*****! *****");
+        assert_eq!(hits, vec![(0, 11, &1), (13, 20, &2)]);
+        assert_eq!(dat.find_all(" ,bad, "), vec![(2, 5, &2)]);
+        assert_eq!(dat.find_all("ba d"), vec![(0, 4, &2)]);
+        assert!(dat.find_all("ba").is_empty());
+
+        dat.set_ignorable(None);
+        assert!(dat.find_all("你 不 配").is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+pub(crate) const BLOCK: usize = 256;
+// Values below this go through the two-level table, which tops out at 4352
+// index entries; that covers every `char`, `u8` and `u16`. Larger `u32`
+// labels fall back to hashing.
+pub(crate) const DENSE_LIMIT: u32 = 0x11_0000;
+
+/// Label-to-code map read with two array lookups: `index` maps the high bits
+/// of a label to a block of `table`, and the low byte picks the code in it.
+/// Block 0 is all zeros and shared by every unused range; code 0 means the
+/// label is not mapped.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CodeMap {
+    pub(crate) index: Vec<u32>,
+    pub(crate) table: Vec<u32>,
+    pub(crate) fallback: HashMap<u32, u32>,
+}
+
+impl Default for CodeMap {
+    fn default() -> Self {
+        Self {
+            index: Vec::new(),
+            table: vec![0; BLOCK],
+            fallback: HashMap::new(),
+        }
+    }
+}
+
+impl CodeMap {
+    /// Maps labels given in code order to codes 1, 2, ...
+    #[cfg(any(feature = "serde", test))]
+    pub(crate) fn from_labels<I: IntoIterator<Item = u32>>(labels: I) -> Self {
+        let mut code_map = Self::default();
+        for (code, value) in (1..).zip(labels) {
+            code_map.insert(value, code);
+        }
+        code_map
+    }
+
+    pub(crate) fn get(&self, value: u32) -> Option<usize> {
+        let code = if value < DENSE_LIMIT {
+            let block = *self.index.get(value as usize / BLOCK)? as usize;
+            self.table[block * BLOCK + value as usize % BLOCK]
+        } else {
+            *self.fallback.get(&value)?
+        };
+        (code != 0).then_some(code as usize)
+    }
+
+    pub(crate) fn insert(&mut self, value: u32, code: usize) {
+        if value >= DENSE_LIMIT {
+            self.fallback.insert(value, code as u32);
+            return;
+        }
+        let high = value as usize / BLOCK;
+        if self.index.len() <= high {
+            self.index.resize(high + 1, 0);
+        }
+        if self.index[high] == 0 {
+            self.index[high] = (self.table.len() / BLOCK) as u32;
+            self.table.resize(self.table.len() + BLOCK, 0);
+        }
+        self.table[self.index[high] as usize * BLOCK + value as usize % BLOCK] = code as u32;
+    }
+
+    /// Mapped labels with their codes, in no particular order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        let dense = self
+            .index
+            .iter()
+            .enumerate()
+            .filter(|&(_, &block)| block != 0)
+            .flat_map(move |(high, &block)| {
+                let start = block as usize * BLOCK;
+                self.table[start..start + BLOCK]
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &code)| code != 0)
+                    .map(move |(low, &code)| ((high * BLOCK + low) as u32, code as usize))
+            });
+        dense.chain(
+            self.fallback
+                .iter()
+                .map(|(&value, &code)| (value, code as usize)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_map() {
+        let mut code_map = CodeMap::from_labels(['a' as u32, '我' as u32, 0xff, DENSE_LIMIT + 7]);
+        code_map.insert('b' as u32, 5);
+
+        assert_eq!(code_map.get('a' as u32), Some(1));
+        assert_eq!(code_map.get('我' as u32), Some(2));
+        assert_eq!(code_map.get(0xff), Some(3));
+        assert_eq!(code_map.get(DENSE_LIMIT + 7), Some(4));
+        assert_eq!(code_map.get('b' as u32), Some(5));
+        assert_eq!(code_map.get('c' as u32), None);
+        assert_eq!(code_map.get('你' as u32), None);
+        assert_eq!(code_map.get(u32::MAX), None);
+        assert_eq!(code_map.table.len(), 3 * BLOCK);
+        assert_eq!(code_map.fallback.len(), 1);
+
+        let mut entries: Vec<_> = code_map.iter().collect();
+        entries.sort_unstable();
+        assert_eq!(
+            entries,
+            vec![
+                (0x61, 1),
+                (0x62, 5),
+                (0xff, 3),
+                ('我' as u32, 2),
+                (DENSE_LIMIT + 7, 4)
+            ]
+        );
+    }
+}
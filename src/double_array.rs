@@ -0,0 +1,197 @@
+use std::cmp::Reverse;
+
+use crate::label::to_key;
+use crate::normalize::Normalized;
+use crate::{Label, Labels, Normalizer, TERMINAL};
+
+/// Read access to the arrays of a trie, implemented by [`Dat`](crate::Dat)
+/// over its vectors and by [`DatView`](crate::DatView) over a byte buffer.
+/// Every query is written once on top of it, so the two answer alike.
+pub(crate) trait DoubleArray<L: Label> {
+    /// `&T` for an owned trie, a decoded `T` for a view.
+    type Value<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize;
+    fn base(&self, state: usize) -> i32;
+    fn check(&self, state: usize) -> i32;
+    fn child(&self, state: usize) -> usize;
+    fn sibling(&self, state: usize) -> usize;
+    fn tail_value(&self, tail_key: i32) -> Option<Self::Value<'_>>;
+    fn normalizer(&self) -> Option<Normalizer<L>>;
+
+    /// Looks up the code map; only called for labels without a fixed
+    /// alphabet.
+    fn map_code(&self, value: u32) -> Option<usize>;
+    /// Returns the label with `code`, counting from 1.
+    fn map_label(&self, code: usize) -> Option<u32>;
+    fn labels_len(&self) -> usize;
+
+    fn code(&self, label: L) -> Option<usize> {
+        if L::ALPHABET.is_some() {
+            Some(label.into_u32() as usize + 1)
+        } else {
+            self.map_code(label.into_u32())
+        }
+    }
+
+    fn label(&self, code: usize) -> Option<L> {
+        let code = code.checked_sub(1)?;
+        if L::ALPHABET.is_some() {
+            L::from_u32(code as u32)
+        } else {
+            L::from_u32(self.map_label(code + 1)?)
+        }
+    }
+
+    fn alphabet_len(&self) -> usize {
+        L::ALPHABET.unwrap_or(self.labels_len())
+    }
+
+    fn normalized<'k, K: Labels<L> + ?Sized>(
+        &self,
+        key: &'k K,
+    ) -> Normalized<impl Iterator<Item = (usize, L)> + 'k, L> {
+        Normalized::new(key.labels(), self.normalizer())
+    }
+
+    fn child_base(&self, state: usize) -> usize {
+        self.base(state).unsigned_abs() as usize
+    }
+
+    fn transition(&self, state: usize, code: usize) -> Option<usize> {
+        let new_state = self.child_base(state) + code;
+        (new_state < self.len() && self.check(new_state) == state as i32).then_some(new_state)
+    }
+
+    fn terminal(&self, state: usize) -> Option<usize> {
+        self.transition(state, TERMINAL)
+    }
+
+    fn value(&self, state: usize) -> Option<Self::Value<'_>> {
+        self.tail_value(self.base(self.terminal(state)?))
+    }
+
+    fn walk<K: Labels<L> + ?Sized>(&self, key: &K) -> Option<usize> {
+        let mut state = 0;
+        for (_, label) in self.normalized(key) {
+            state = self.transition(state, self.code(label)?)?;
+        }
+        Some(state)
+    }
+
+    /// Returns the codes of the children of `state`, TERMINAL first. The
+    /// sibling chain is cut at the alphabet size, so a corrupted view cannot
+    /// loop forever.
+    fn children(&self, state: usize) -> Vec<usize> {
+        let base = self.child_base(state);
+        let mut codes = Vec::new();
+        if self.terminal(state).is_some() {
+            codes.push(TERMINAL);
+        }
+        let mut code = self.child(state);
+        while code != 0
+            && codes.len() <= self.alphabet_len()
+            && self.transition(state, code).is_some()
+        {
+            codes.push(code);
+            code = self.sibling(base + code);
+        }
+        codes
+    }
+
+    fn search_prefixes<K: Labels<L> + ?Sized>(&self, key: &K) -> Vec<(L::Key, Self::Value<'_>)> {
+        let mut state = 0;
+        let mut results: Vec<_> = self
+            .value(state)
+            .map(|value| (L::Key::default(), value))
+            .into_iter()
+            .collect();
+        // Keys are copied from the query rather than rebuilt from normalized
+        // labels, so each one is a prefix of `key` as given.
+        let mut original = key.labels().peekable();
+        let mut current_key = L::Key::default();
+
+        for (end, label) in self.normalized(key) {
+            match self
+                .code(label)
+                .and_then(|code| self.transition(state, code))
+            {
+                Some(new_state) => state = new_state,
+                None => break,
+            }
+
+            if let (Some(end), Some(value)) = (end, self.value(state)) {
+                while let Some((_, label)) = original.next_if(|&(next, _)| next <= end) {
+                    label.push(&mut current_key);
+                }
+                results.push((current_key.clone(), value));
+            }
+        }
+
+        results
+    }
+
+    fn longest_match<I: Iterator<Item = (usize, L)>>(
+        &self,
+        labels: I,
+    ) -> Option<(usize, usize, Self::Value<'_>)> {
+        let mut state = 0;
+        let mut count = 0;
+        let mut longest = self.value(state).map(|value| (0, 0, value));
+
+        for (end, label) in Normalized::new(labels, self.normalizer()) {
+            match self
+                .code(label)
+                .and_then(|code| self.transition(state, code))
+            {
+                Some(new_state) => state = new_state,
+                None => break,
+            }
+
+            if let Some(end) = end {
+                count += 1;
+                if let Some(value) = self.value(state) {
+                    longest = Some((end, count, value));
+                }
+            }
+        }
+
+        longest
+    }
+
+    /// Starts a depth-first walk below `prefix` for [`DoubleArray::predict_next`].
+    fn predict_stack<K: Labels<L> + ?Sized>(&self, prefix: &K) -> Vec<(usize, L::Key)> {
+        self.walk(prefix)
+            .map(|state| {
+                let labels = self.normalized(prefix).map(|(_, label)| label);
+                (state, to_key(labels))
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Pops nodes until one holds a value, pushing children so that they
+    /// come out in label order.
+    fn predict_next(&self, stack: &mut Vec<(usize, L::Key)>) -> Option<(L::Key, Self::Value<'_>)> {
+        while let Some((state, key)) = stack.pop() {
+            let base = self.child_base(state);
+            let mut children: Vec<(L, usize)> = self
+                .children(state)
+                .into_iter()
+                .filter_map(|code| Some((self.label(code)?, base + code)))
+                .collect();
+            children.sort_unstable_by_key(|&(label, _)| Reverse(label));
+            for (label, child) in children {
+                let mut child_key = key.clone();
+                label.push(&mut child_key);
+                stack.push((child, child_key));
+            }
+            if let Some(value) = self.value(state) {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
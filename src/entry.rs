@@ -0,0 +1,181 @@
+use crate::double_array::DoubleArray;
+use crate::{Dat, Label, Labels};
+
+/// A view into a single key of a [`Dat`], created by [`Dat::entry`].
+pub enum Entry<'a, T, L = char, K: ?Sized = str> {
+    Occupied(OccupiedEntry<'a, T, L, K>),
+    Vacant(VacantEntry<'a, T, L, K>),
+}
+
+/// An entry for a key that is in the trie.
+pub struct OccupiedEntry<'a, T, L = char, K: ?Sized = str> {
+    dat: &'a mut Dat<T, L>,
+    key: &'a K,
+    tail_key: i32,
+}
+
+/// An entry for a key that is not in the trie. It remembers where the walk
+/// stopped, so inserting only adds the missing nodes.
+pub struct VacantEntry<'a, T, L = char, K: ?Sized = str> {
+    dat: &'a mut Dat<T, L>,
+    key: &'a K,
+    state: usize,
+    depth: usize,
+}
+
+impl<'a, T, L: Label, K: Labels<L> + ?Sized> Entry<'a, T, L, K> {
+    pub fn key(&self) -> &'a K {
+        match self {
+            Entry::Occupied(entry) => entry.key,
+            Entry::Vacant(entry) => entry.key,
+        }
+    }
+
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, T: Default, L: Label, K: Labels<L> + ?Sized> Entry<'a, T, L, K> {
+    pub fn or_default(self) -> &'a mut T {
+        self.or_insert_with(T::default)
+    }
+}
+
+impl<'a, T, L: Label, K: Labels<L> + ?Sized> OccupiedEntry<'a, T, L, K> {
+    pub fn key(&self) -> &'a K {
+        self.key
+    }
+
+    pub fn get(&self) -> &T {
+        &self.dat.tail[&self.tail_key]
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dat.tail.get_mut(&self.tail_key).unwrap()
+    }
+
+    pub fn into_mut(self) -> &'a mut T {
+        self.dat.tail.get_mut(&self.tail_key).unwrap()
+    }
+
+    pub fn insert(&mut self, value: T) -> T {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> T {
+        self.dat.remove(self.key).unwrap()
+    }
+}
+
+impl<'a, T, L: Label, K: Labels<L> + ?Sized> VacantEntry<'a, T, L, K> {
+    pub fn key(&self) -> &'a K {
+        self.key
+    }
+
+    pub fn insert(self, value: T) -> &'a mut T {
+        let suffix = self
+            .dat
+            .normalized(self.key)
+            .skip(self.depth)
+            .map(|(_, label)| label);
+        let tail_key = self.dat.attach(self.state, suffix, value);
+        self.dat.tail.get_mut(&tail_key).unwrap()
+    }
+}
+
+impl<T, L: Label> Dat<T, L> {
+    /// Gets the entry for `key` for in-place manipulation, walking the trie
+    /// once whether the key is then updated or inserted.
+    pub fn entry<'a, K: Labels<L> + ?Sized>(&'a mut self, key: &'a K) -> Entry<'a, T, L, K> {
+        let (state, depth) = self.walk_prefix(key);
+        let depth = match depth {
+            Some(depth) => depth,
+            None => match self.terminal(state) {
+                Some(leaf) => {
+                    let tail_key = self.base[leaf];
+                    return Entry::Occupied(OccupiedEntry {
+                        dat: self,
+                        key,
+                        tail_key,
+                    });
+                }
+                None => self.normalized(key).count(),
+            },
+        };
+        Entry::Vacant(VacantEntry {
+            dat: self,
+            key,
+            state,
+            depth,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Dat, Entry};
+
+    #[test]
+    fn test_entry_counts() {
+        let mut dat: Dat<usize> = Dat::new();
+        for word in "我 喜欢 你 我 喜 你 我".split(' ') {
+            *dat.entry(word).or_default() += 1;
+        }
+
+        assert_eq!(
+            dat.iter().collect::<Vec<_>>(),
+            vec![
+                ("你".to_string(), &2),
+                ("喜".to_string(), &1),
+                ("喜欢".to_string(), &1),
+                ("我".to_string(), &3)
+            ]
+        );
+        assert_eq!(dat.tail.len(), 4);
+    }
+
+    #[test]
+    fn test_entry_api() {
+        let mut dat: Dat<Vec<&str>> = Dat::new();
+        dat.entry("ab").or_insert_with(|| vec!["x"]);
+        dat.entry("ab")
+            .and_modify(|tags| tags.push("y"))
+            .or_insert(vec![]);
+        dat.entry("abc")
+            .and_modify(|tags| tags.push("y"))
+            .or_insert(vec!["z"]);
+
+        assert_eq!(dat.lookup("ab"), Some(&vec!["x", "y"]));
+        assert_eq!(dat.lookup("abc"), Some(&vec!["z"]));
+        assert_eq!(dat.entry("a").key(), "a");
+        assert!(matches!(dat.entry("a"), Entry::Vacant(_)));
+
+        match dat.entry("ab") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(vec!["w"]), vec!["x", "y"]);
+                assert_eq!(entry.remove(), vec!["w"]);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(dat.lookup("ab"), None);
+        assert_eq!(dat.lookup("abc"), Some(&vec!["z"]));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::double_array::DoubleArray;
+use crate::label::to_key;
+use crate::{Dat, Label, Labels, TERMINAL};
+
+impl<T, L: Label> Dat<T, L> {
+    /// Returns every key within Levenshtein distance `max_distance` of
+    /// `query`, along with the distance and the value, ordered by key.
+    ///
+    /// The trie is walked depth first with one row of the edit distance
+    /// table per node, so shared prefixes are computed once, and a branch is
+    /// cut as soon as every cell of its row exceeds the bound.
+    pub fn fuzzy<K: Labels<L> + ?Sized>(
+        &self,
+        query: &K,
+        max_distance: usize,
+    ) -> Vec<(L::Key, usize, &T)> {
+        self.fuzzy_search(query, max_distance, false)
+    }
+
+    /// Same as [`Dat::fuzzy`], also counting a swap of two adjacent labels
+    /// as a single edit (optimal string alignment distance).
+    pub fn fuzzy_damerau<K: Labels<L> + ?Sized>(
+        &self,
+        query: &K,
+        max_distance: usize,
+    ) -> Vec<(L::Key, usize, &T)> {
+        self.fuzzy_search(query, max_distance, true)
+    }
+
+    fn fuzzy_search<K: Labels<L> + ?Sized>(
+        &self,
+        query: &K,
+        max_distance: usize,
+        transpositions: bool,
+    ) -> Vec<(L::Key, usize, &T)> {
+        // Labels missing from the code map can only be substituted.
+        let query: Vec<Option<usize>> = self
+            .normalized(query)
+            .map(|(_, label)| self.code(label))
+            .collect();
+        let mut search = Fuzzy {
+            dat: self,
+            rows: vec![(0..=query.len()).collect()],
+            query,
+            max_distance,
+            transpositions,
+            path: Vec::new(),
+            results: Vec::new(),
+        };
+        search.visit(0);
+        search.results
+    }
+}
+
+struct Fuzzy<'a, T, L: Label> {
+    dat: &'a Dat<T, L>,
+    query: Vec<Option<usize>>,
+    max_distance: usize,
+    transpositions: bool,
+    // Codes from the root to the current node, and the distance row of every
+    // node on the way, the root's included.
+    path: Vec<usize>,
+    rows: Vec<Vec<usize>>,
+    results: Vec<(L::Key, usize, &'a T)>,
+}
+
+impl<T, L: Label> Fuzzy<'_, T, L> {
+    fn visit(&mut self, state: usize) {
+        let row = &self.rows[self.rows.len() - 1];
+        let distance = row[self.query.len()];
+        let closest = row.iter().copied().min().unwrap_or(0);
+
+        if distance <= self.max_distance {
+            if let Some(value) = self.dat.value(state) {
+                let key = to_key(self.path.iter().filter_map(|&code| self.dat.label(code)));
+                self.results.push((key, distance, value));
+            }
+        }
+        if closest > self.max_distance {
+            return;
+        }
+
+        let base = self.dat.child_base(state);
+        let mut codes = self.dat.children(state);
+        codes.retain(|&code| code != TERMINAL);
+        codes.sort_by_key(|&code| self.dat.label(code));
+        for code in codes {
+            let row = self.next_row(code);
+            self.path.push(code);
+            self.rows.push(row);
+            self.visit(base + code);
+            self.rows.pop();
+            self.path.pop();
+        }
+    }
+
+    fn next_row(&self, code: usize) -> Vec<usize> {
+        let last = &self.rows[self.rows.len() - 1];
+        let mut row = Vec::with_capacity(last.len());
+        row.push(last[0] + 1);
+
+        for (j, &label) in self.query.iter().enumerate() {
+            let cost = usize::from(label != Some(code));
+            let mut distance = (last[j] + cost).min(last[j + 1] + 1).min(row[j] + 1);
+            if self.transpositions && j > 0 && self.query[j - 1] == Some(code) {
+                if let Some(&previous) = self.path.last() {
+                    if label == Some(previous) {
+                        distance = distance.min(self.rows[self.rows.len() - 2][j - 1] + 1);
+                    }
+                }
+            }
+            row.push(distance);
+        }
+        row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dat;
+
+    fn distance(a: &str, b: &str, transpositions: bool) -> usize {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..=a.len() {
+            for j in 0..=b.len() {
+                table[i][j] = if i == 0 || j == 0 {
+                    i + j
+                } else {
+                    let cost = usize::from(a[i - 1] != b[j - 1]);
+                    (table[i - 1][j - 1] + cost)
+                        .min(table[i - 1][j] + 1)
+                        .min(table[i][j - 1] + 1)
+                };
+                if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1]
+                {
+                    table[i][j] = table[i][j].min(table[i - 2][j - 2] + 1);
+                }
+            }
+        }
+        table[a.len()][b.len()]
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let mut dat = Dat::new();
+        dat.load(vec![("apple", 1), ("apply", 2), ("ample", 3), ("maple", 4)]);
+
+        assert_eq!(dat.fuzzy("apple", 0), vec![("apple".to_string(), 0, &1)]);
+        assert_eq!(
+            dat.fuzzy("appel", 2),
+            vec![("apple".to_string(), 2, &1), ("apply".to_string(), 2, &2)]
+        );
+        assert_eq!(
+            dat.fuzzy_damerau("appel", 1),
+            vec![("apple".to_string(), 1, &1)]
+        );
+        assert!(dat.fuzzy("banana", 2).is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_matches_brute_force() {
+        let keys = [
+            "",
+            "a",
+            "ab",
+            "ba",
+            "abc",
+            "acb",
+            "bca",
+            "abcd",
+            "我",
+            "我喜欢",
+            "喜欢我",
+            "你不配",
+        ];
+        let mut dat = Dat::new();
+        dat.load(keys.iter().zip(0..).collect());
+
+        for query in ["", "a", "ab", "bac", "abdc", "我欢喜", "你配", "xyz"] {
+            for max_distance in 0..4 {
+                for transpositions in [false, true] {
+                    let mut expected = Vec::new();
+                    for (&key, value) in keys.iter().zip(0..) {
+                        let distance = distance(key, query, transpositions);
+                        if distance <= max_distance {
+                            expected.push((key.to_string(), distance, value));
+                        }
+                    }
+                    expected.sort();
+
+                    let found = if transpositions {
+                        dat.fuzzy_damerau(query, max_distance)
+                    } else {
+                        dat.fuzzy(query, max_distance)
+                    };
+                    let found: Vec<_> = found
+                        .into_iter()
+                        .map(|(key, distance, &value)| (key, distance, value))
+                        .collect();
+                    assert_eq!(found, expected, "{query:?} within {max_distance}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_labels() {
+        let mut dat: Dat<i32, u32> = Dat::new();
+        dat.load(vec![(&[1, 2, 3][..], 1), (&[1, 3][..], 2)]);
+
+        assert_eq!(
+            dat.fuzzy(&[1, 2], 1),
+            vec![(vec![1, 2, 3], 1, &1), (vec![1, 3], 1, &2)]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::double_array::DoubleArray;
+use crate::{Dat, Label, Labels};
+
+/// Iterator over the keys starting with a prefix in label order, created by
+/// [`Dat::predict`].
+pub struct Predict<'a, T, L: Label = char> {
+    dat: &'a Dat<T, L>,
+    stack: Vec<(usize, L::Key)>,
+}
+
+impl<'a, T, L: Label> Iterator for Predict<'a, T, L> {
+    type Item = (L::Key, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.dat.predict_next(&mut self.stack)
+    }
+}
+
+/// Iterator over all entries in label order, created by [`Dat::iter`].
+pub struct Iter<'a, T, L: Label = char>(Predict<'a, T, L>);
+
+impl<'a, T, L: Label> Iterator for Iter<'a, T, L> {
+    type Item = (L::Key, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// Iterator over all keys in label order, created by [`Dat::keys`].
+pub struct Keys<'a, T, L: Label = char>(Predict<'a, T, L>);
+
+impl<'a, T, L: Label> Iterator for Keys<'a, T, L> {
+    type Item = L::Key;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+}
+
+/// Iterator over all values in key order, created by [`Dat::values`].
+pub struct Values<'a, T, L: Label = char>(Predict<'a, T, L>);
+
+impl<'a, T, L: Label> Iterator for Values<'a, T, L> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+}
+
+impl<'a, T, L: Label> IntoIterator for &'a Dat<T, L> {
+    type Item = (L::Key, &'a T);
+    type IntoIter = Iter<'a, T, L>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, L: Label> Dat<T, L> {
+    /// Returns an iterator over all entries, ordered by key.
+    pub fn iter(&self) -> Iter<'_, T, L> {
+        Iter(self.predict(&[]))
+    }
+
+    pub fn keys(&self) -> Keys<'_, T, L> {
+        Keys(self.predict(&[]))
+    }
+
+    pub fn values(&self) -> Values<'_, T, L> {
+        Values(self.predict(&[]))
+    }
+
+    /// Returns an iterator over every key that starts with `prefix`, along
+    /// with its value.
+    pub fn predict<K: Labels<L> + ?Sized>(&self, prefix: &K) -> Predict<'_, T, L> {
+        Predict {
+            dat: self,
+            stack: self.predict_stack(prefix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dat;
+
+    #[test]
+    fn test_predict() {
+        let mut dat = Dat::new();
+        dat.load(vec![
+            ("我", 1),
+            ("我喜欢", 2),
+            ("我喜欢你", 3),
+            ("你不配", 4),
+        ]);
+
+        let results: Vec<(String, &i32)> = dat.predict("我喜").collect();
+        assert_eq!(
+            results,
+            vec![("我喜欢".to_string(), &2), ("我喜欢你".to_string(), &3)]
+        );
+        assert_eq!(dat.predict("我").count(), 3);
+        assert_eq!(dat.predict("").count(), 4);
+        assert_eq!(dat.predict("他").count(), 0);
+        assert_eq!(dat.predict("我喜欢你呀").count(), 0);
+    }
+
+    #[test]
+    fn test_predict_prefix_first() {
+        let mut dat = Dat::new();
+        dat.load(vec![("abc", 3), ("a", 1), ("ab", 2)]);
+
+        let keys: Vec<String> = dat.predict("a").map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["a", "ab", "abc"]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut dat = Dat::new();
+        dat.load(vec![("b", 2), ("ba", 3), ("你", 5), ("a", 1), ("z", 4)]);
+
+        assert_eq!(
+            dat.iter().collect::<Vec<_>>(),
+            vec![
+                ("a".to_string(), &1),
+                ("b".to_string(), &2),
+                ("ba".to_string(), &3),
+                ("z".to_string(), &4),
+                ("你".to_string(), &5)
+            ]
+        );
+        assert_eq!(
+            dat.keys().collect::<Vec<_>>(),
+            vec!["a", "b", "ba", "z", "你"]
+        );
+        assert_eq!(dat.values().collect::<Vec<_>>(), vec![&1, &2, &3, &4, &5]);
+        assert_eq!((&dat).into_iter().count(), 5);
+    }
+
+    #[test]
+    fn test_iter_ignores_insertion_order() {
+        let keys = ["key", "k", "abc", "ab", "键", "z"];
+        let mut forward = Dat::new();
+        let mut backward = Dat::new();
+        for (i, key) in keys.iter().enumerate() {
+            forward.append(key, i);
+        }
+        for (i, key) in keys.iter().enumerate().rev() {
+            backward.append(key, i);
+        }
+
+        let values: Vec<usize> = (0..keys.len()).collect();
+        let mut sorted: Vec<(String, &usize)> = keys
+            .iter()
+            .map(|key| key.to_string())
+            .zip(&values)
+            .collect();
+        sorted.sort();
+        assert_eq!(forward.iter().collect::<Vec<_>>(), sorted);
+        assert_eq!(backward.iter().collect::<Vec<_>>(), sorted);
+    }
+}
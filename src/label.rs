@@ -0,0 +1,134 @@
+use std::hash::Hash;
+
+/// Element type of the keys stored in a [`Dat`](crate::Dat).
+///
+/// Labels are stored as `u32` in the binary format, so they must round-trip
+/// through [`Label::into_u32`] and [`Label::from_u32`] in order.
+pub trait Label: Copy + Ord + Hash {
+    /// Owned key returned by queries: `String` for `char`, `Vec<Self>` for
+    /// the integer labels.
+    type Key: Default + Clone;
+
+    /// Size of a fixed alphabet coded directly as `into_u32() + 1`, skipping
+    /// the code map. `None` assigns codes in first-seen order instead.
+    const ALPHABET: Option<usize> = None;
+
+    fn push(self, key: &mut Self::Key);
+    fn into_u32(self) -> u32;
+    fn from_u32(value: u32) -> Option<Self>;
+}
+
+impl Label for char {
+    type Key = String;
+
+    fn push(self, key: &mut String) {
+        key.push(self);
+    }
+
+    fn into_u32(self) -> u32 {
+        self as u32
+    }
+
+    fn from_u32(value: u32) -> Option<Self> {
+        char::from_u32(value)
+    }
+}
+
+macro_rules! impl_label_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Label for $ty {
+                type Key = Vec<$ty>;
+
+                fn push(self, key: &mut Vec<$ty>) {
+                    key.push(self);
+                }
+
+                fn into_u32(self) -> u32 {
+                    self as u32
+                }
+
+                fn from_u32(value: u32) -> Option<Self> {
+                    <$ty>::try_from(value).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_label_int!(u16, u32);
+
+// Bytes index UTF-8 text directly: `"键".as_bytes()` walks three transitions
+// with no hashing, at the cost of deeper tries than `char` labels.
+impl Label for u8 {
+    type Key = Vec<u8>;
+
+    const ALPHABET: Option<usize> = Some(256);
+
+    fn push(self, key: &mut Vec<u8>) {
+        key.push(self);
+    }
+
+    fn into_u32(self) -> u32 {
+        self as u32
+    }
+
+    fn from_u32(value: u32) -> Option<Self> {
+        u8::try_from(value).ok()
+    }
+}
+
+/// A borrowed key readable as a sequence of labels: `str` and `String` for
+/// `char`, and slices, arrays and vectors of any label.
+pub trait Labels<L> {
+    /// Returns each label with the offset just past it, in bytes for `str`
+    /// and in labels for slices.
+    fn labels(&self) -> impl Iterator<Item = (usize, L)> + '_;
+}
+
+impl Labels<char> for str {
+    fn labels(&self) -> impl Iterator<Item = (usize, char)> + '_ {
+        self.char_indices().map(|(i, ch)| (i + ch.len_utf8(), ch))
+    }
+}
+
+impl Labels<char> for String {
+    fn labels(&self) -> impl Iterator<Item = (usize, char)> + '_ {
+        self.as_str().labels()
+    }
+}
+
+impl<L: Label> Labels<L> for [L] {
+    fn labels(&self) -> impl Iterator<Item = (usize, L)> + '_ {
+        self.iter()
+            .copied()
+            .zip(1..)
+            .map(|(label, end)| (end, label))
+    }
+}
+
+impl<L: Label, const N: usize> Labels<L> for [L; N] {
+    fn labels(&self) -> impl Iterator<Item = (usize, L)> + '_ {
+        self.as_slice().labels()
+    }
+}
+
+impl<L: Label> Labels<L> for Vec<L> {
+    fn labels(&self) -> impl Iterator<Item = (usize, L)> + '_ {
+        self.as_slice().labels()
+    }
+}
+
+impl<L, K: Labels<L> + ?Sized> Labels<L> for &K {
+    fn labels(&self) -> impl Iterator<Item = (usize, L)> + '_ {
+        (**self).labels()
+    }
+}
+
+pub(crate) fn to_key<L: Label>(labels: impl Iterator<Item = L>) -> L::Key {
+    let mut owned = L::Key::default();
+    for label in labels {
+        label.push(&mut owned);
+    }
+    owned
+}
@@ -1,174 +1,482 @@
+mod ac;
+mod binary;
+mod builder;
+mod censor;
+mod code_map;
+mod double_array;
+mod entry;
+mod fuzzy;
+mod iter;
+mod label;
+pub mod normalize;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod view;
+
 use std::collections::HashMap;
 
-pub struct Dat<T: Copy> {
+use code_map::CodeMap;
+use double_array::DoubleArray;
+
+pub use ac::{AhoCorasick, FindIter};
+pub use binary::Codec;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iter::{Iter, Keys, Predict, Values};
+pub use label::{Label, Labels};
+pub use normalize::Normalizer;
+pub use view::DatView;
+
+// Code of the leaf transition marking the end of a key. The leaf's `base`
+// holds the negative tail key, so a word end is a child of its own and the
+// node keeps its base for branching.
+const TERMINAL: usize = 0;
+const MAX_TRIAL: usize = 8;
+
+pub struct Dat<T, L = char> {
     base: Vec<i32>,
     check: Vec<i32>,
+    child: Vec<usize>,
+    sibling: Vec<usize>,
     tail: HashMap<i32, T>,
-    code_map: HashMap<char, usize>,
+    tail_free: Vec<i32>,
+    free_head: usize,
+    code_map: CodeMap,
+    labels: Vec<L>,
+    normalizer: Option<Normalizer<L>>,
+    ignorable: Option<fn(char) -> bool>,
 }
 
-impl<T: Copy> Default for Dat<T> {
+impl<T, L> Default for Dat<T, L> {
     fn default() -> Self {
         Self {
             base: vec![1],
             check: vec![0],
+            child: vec![0],
+            sibling: vec![0],
             tail: HashMap::new(),
-            code_map: HashMap::new(),
+            tail_free: Vec::new(),
+            free_head: 0,
+            code_map: CodeMap::default(),
+            labels: Vec::new(),
+            normalizer: None,
+            ignorable: None,
         }
     }
 }
 
-impl<T: Copy> Dat<T> {
+impl<T, L: Label> Dat<T, L> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    fn get_code(&mut self, ch: char) -> usize {
-        let len = self.code_map.len();
-        *self.code_map.entry(ch).or_insert_with(|| {
-            let code = len + 1;
-            code
+    /// Creates an empty trie that passes every key, on insertion and in
+    /// queries, through `normalizer`. Keys are stored normalized, while
+    /// offsets returned by queries refer to the original input.
+    pub fn with_normalizer(normalizer: Normalizer<L>) -> Self {
+        Self {
+            normalizer: Some(normalizer),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the normalizer, which is not saved by [`Dat::write_to`] or serde.
+    /// Keys already in the trie are not rewritten, so they must be in
+    /// normalized form.
+    pub fn set_normalizer(&mut self, normalizer: Option<Normalizer<L>>) {
+        self.normalizer = normalizer;
+    }
+
+    /// Makes `alias` share the code of `label`, so keys and queries treat
+    /// the two as the same label. Keys rebuilt from the trie, as by
+    /// [`Dat::iter`], are spelled with `label`.
+    ///
+    /// Returns `false` if `alias` already has a code of its own, from stored
+    /// keys or another class, or if `L` has a fixed alphabet such as `u8`.
+    pub fn add_equivalent(&mut self, alias: L, label: L) -> bool {
+        if L::ALPHABET.is_some() {
+            return alias == label;
+        }
+        // `label` only gets a code once the call is known to succeed, so a
+        // rejected alias leaves the code table as it was.
+        match self.code(alias) {
+            Some(existing) => self.code(label) == Some(existing),
+            None => {
+                let code = self.get_code(label);
+                self.code_map.insert(alias.into_u32(), code);
+                true
+            }
+        }
+    }
+
+    /// Returns the labels added by [`Dat::add_equivalent`], each with the
+    /// label it stands for.
+    pub fn equivalents(&self) -> impl Iterator<Item = (L, L)> + '_ {
+        self.code_map.iter().filter_map(|(value, code)| {
+            let label = self.labels[code - 1];
+            (label.into_u32() != value).then(|| (L::from_u32(value).unwrap(), label))
         })
     }
 
+    fn get_code(&mut self, label: L) -> usize {
+        if let Some(code) = self.code(label) {
+            return code;
+        }
+        self.labels.push(label);
+        self.code_map.insert(label.into_u32(), self.labels.len());
+        self.labels.len()
+    }
+
+    fn is_vacant(&self, state: usize) -> bool {
+        self.check[state] < 0
+    }
+
+    // Follows `key` as far as the trie goes and returns the last state along
+    // with the number of labels matched if it stopped before the end.
+    fn walk_prefix<K: Labels<L> + ?Sized>(&self, key: &K) -> (usize, Option<usize>) {
+        let mut state = 0;
+        for (depth, (_, label)) in self.normalized(key).enumerate() {
+            match self
+                .code(label)
+                .and_then(|code| self.transition(state, code))
+            {
+                Some(new_state) => state = new_state,
+                None => return (state, Some(depth)),
+            }
+        }
+        (state, None)
+    }
+
+    fn unlink(&mut self, parent: usize, code: usize) {
+        let base = self.child_base(parent);
+        if self.child[parent] == code {
+            self.child[parent] = self.sibling[base + code];
+            return;
+        }
+        let mut prev = self.child[parent];
+        while self.sibling[base + prev] != code {
+            prev = self.sibling[base + prev];
+        }
+        self.sibling[base + prev] = self.sibling[base + code];
+    }
+
+    fn alloc_tail(&mut self, value: T) -> i32 {
+        let tail_key = self
+            .tail_free
+            .pop()
+            .unwrap_or(-((self.tail.len() + self.tail_free.len()) as i32) - 1);
+        self.tail.insert(tail_key, value);
+        tail_key
+    }
+
+    // Vacant slots form a circular doubly linked list threaded through the
+    // arrays: `check` holds the negated next slot and `base` the negated
+    // previous one. The root never becomes vacant, so 0 marks an empty list.
+    // While vacant, `child` counts the failed base trials anchored at the
+    // slot; after MAX_TRIAL failures it leaves the list and links to itself.
+    fn push_vacant(&mut self, state: usize) {
+        let head = self.free_head;
+        if head == 0 {
+            self.base[state] = -(state as i32);
+            self.check[state] = -(state as i32);
+            self.free_head = state;
+        } else {
+            let last = -self.base[head] as usize;
+            self.check[last] = -(state as i32);
+            self.base[state] = -(last as i32);
+            self.check[state] = -(head as i32);
+            self.base[head] = -(state as i32);
+        }
+        self.child[state] = 0;
+        self.sibling[state] = 0;
+    }
+
+    fn release(&mut self, state: usize) {
+        self.push_vacant(state);
+        self.free_head = state;
+    }
+
+    fn unlink_vacant(&mut self, state: usize) {
+        let next = -self.check[state] as usize;
+        let prev = -self.base[state] as usize;
+        if self.free_head == state {
+            self.free_head = if next == state { 0 } else { next };
+        }
+        self.check[prev] = -(next as i32);
+        self.base[next] = -(prev as i32);
+        self.base[state] = -(state as i32);
+        self.check[state] = -(state as i32);
+    }
+
+    fn occupy(&mut self, state: usize) {
+        self.resize(state + 1);
+        self.unlink_vacant(state);
+        self.base[state] = 0;
+        self.check[state] = 0;
+        self.child[state] = 0;
+    }
+
     fn resize(&mut self, size: usize) {
         while self.base.len() < size {
+            let state = self.base.len();
             self.base.push(0);
             self.check.push(0);
+            self.child.push(0);
+            self.sibling.push(0);
+            self.push_vacant(state);
         }
     }
 
-    fn can_use_base(&mut self, base: usize, suffix: &str) -> bool {
-        for ch in suffix.chars() {
-            let ch_code = self.get_code(ch);
-            let new_state = base + ch_code;
+    fn can_use_base(&self, base: usize, codes: &[usize]) -> bool {
+        codes.iter().all(|&code| {
+            let new_state = base + code;
+            new_state >= self.base.len() || self.is_vacant(new_state)
+        })
+    }
 
-            if new_state < self.base.len()
-                && (self.base[new_state] != 0 || self.check[new_state] != 0)
-            {
-                return false;
+    fn find_base(&mut self, codes: &[usize]) -> usize {
+        let first = *codes.iter().min().unwrap();
+        if self.free_head != 0 {
+            let mut state = self.free_head;
+            let last = -self.base[state] as usize;
+            loop {
+                let next = -self.check[state] as usize;
+                if state > first {
+                    if self.can_use_base(state - first, codes) {
+                        return state - first;
+                    }
+                    self.child[state] += 1;
+                    if self.child[state] >= MAX_TRIAL {
+                        self.unlink_vacant(state);
+                    }
+                }
+                if state == last {
+                    break;
+                }
+                state = next;
             }
         }
-        true
+        self.base.len().max(first + 1) - first
     }
 
-    fn relocate(&mut self, state: usize, old_base: usize, new_base: usize) {
-        for (_, &code) in &self.code_map {
+    fn relocate(&mut self, state: usize, codes: &[usize], new_base: usize) {
+        let old_base = self.child_base(state);
+        for &code in codes {
             let old_state = old_base + code;
             let new_state = new_base + code;
 
-            if old_state < self.base.len() && self.check[old_state] == state as i32 {
-                while self.base.len() < (new_state + 1) {
-                    self.base.push(0);
-                    self.check.push(0);
-                }
-                self.base[new_state] = self.base[old_state];
-                self.check[new_state] = state as i32;
-                for j in 0..self.base.len() {
-                    if self.check[j] == old_state as i32 {
-                        self.check[j] = new_state as i32;
-                    }
-                }
-                self.base[old_state] = 0;
-                self.check[old_state] = 0;
+            self.occupy(new_state);
+            self.base[new_state] = self.base[old_state];
+            self.check[new_state] = state as i32;
+            self.child[new_state] = self.child[old_state];
+            self.sibling[new_state] = self.sibling[old_state];
+            if code == TERMINAL {
+                self.release(old_state);
+                continue;
             }
+            for grandchild in self.children(old_state) {
+                let child_state = self.child_base(old_state) + grandchild;
+                self.check[child_state] = new_state as i32;
+            }
+            self.release(old_state);
         }
         self.base[state] = new_base as i32;
     }
 
-    pub fn append(&mut self, key: &str, value: T) {
-        let mut state = 0;
+    fn add_child(&mut self, mut state: usize, code: usize) -> usize {
+        // Children are only listed when the slot is taken or the node has no
+        // base yet, since walking the sibling chain of a wide node such as the
+        // root on every insertion would dominate the build.
+        let mut new_state = self.child_base(state) + code;
+        if self.base[state] == 0 || !self.can_use_base(self.child_base(state), &[code]) {
+            let mut codes = self.children(state);
+            if codes.is_empty() {
+                self.base[state] = self.find_base(&[code]) as i32;
+                new_state = self.child_base(state) + code;
+            } else {
+                let other = self.check[new_state] as usize;
+                let other_codes = self.children(other);
+
+                if other_codes.len() <= codes.len() {
+                    let moved = (state != 0 && self.check[state] as usize == other)
+                        .then(|| state - self.child_base(other));
+                    let new_base = self.find_base(&other_codes);
+                    self.relocate(other, &other_codes, new_base);
+                    if let Some(moved) = moved {
+                        state = new_base + moved;
+                    }
+                } else {
+                    codes.push(code);
+                    let new_base = self.find_base(&codes);
+                    codes.pop();
+                    self.relocate(state, &codes, new_base);
+                    new_state = new_base + code;
+                }
+            }
+        }
 
-        for (i, ch) in key.chars().enumerate() {
-            let ch_code = self.get_code(ch);
-            let new_state = (self.base[state].abs() as usize) + ch_code;
-            self.resize(new_state + 1);
+        self.occupy(new_state);
+        self.check[new_state] = state as i32;
+        if code != TERMINAL {
+            self.sibling[new_state] = self.child[state];
+            self.child[state] = code;
+        }
+        new_state
+    }
 
-            if self.base[new_state] == 0 && self.check[new_state] == 0 {
-                self.base[new_state] = self.base.len() as i32;
-                self.check[new_state] = state as i32;
-            } else if self.check[new_state] != state as i32 {
-                let old_base = self.base[state].abs() as usize;
-                let mut new_base = 1;
+    pub fn append<K: Labels<L> + ?Sized>(&mut self, key: &K, value: T) {
+        self.insert(key, value);
+    }
 
-                while !self.can_use_base(new_base, &key[i..]) {
-                    new_base += 1;
+    /// Inserts `key` with `value` and returns the value it replaced, if any.
+    ///
+    /// An existing key keeps its tail entry, which is overwritten in place.
+    pub fn insert<K: Labels<L> + ?Sized>(&mut self, key: &K, value: T) -> Option<T> {
+        let (state, depth) = self.walk_prefix(key);
+        match depth {
+            Some(depth) => {
+                let suffix = self.normalized(key).skip(depth).map(|(_, label)| label);
+                self.attach(state, suffix, value);
+            }
+            None => match self.terminal(state) {
+                Some(leaf) => return self.tail.insert(self.base[leaf], value),
+                None => {
+                    self.attach(state, std::iter::empty(), value);
                 }
+            },
+        }
+        None
+    }
 
-                self.relocate(state, old_base, new_base);
-                let new_state = new_base + ch_code;
-                self.resize(new_state + 1);
-                self.base[new_state] = self.base.len() as i32;
-                self.check[new_state] = state as i32;
-            }
-            state = new_state;
+    // Adds the nodes for `suffix` below `state`, which must have no child for
+    // its first label, and stores `value` at the end. Returns the tail key.
+    fn attach(&mut self, mut state: usize, suffix: impl Iterator<Item = L>, value: T) -> i32 {
+        for label in suffix {
+            let code = self.get_code(label);
+            state = self.add_child(state, code);
         }
+        let leaf = self.add_child(state, TERMINAL);
+        let tail_key = self.alloc_tail(value);
+        self.base[leaf] = tail_key;
+        tail_key
+    }
 
-        let tail_key = -(self.tail.len() as i32) - 1;
-        self.tail.insert(tail_key, value);
-        self.base[state] = tail_key;
+    /// Removes `key` from the trie and returns its value.
+    ///
+    /// The tail entry is released and every node left without a terminal or
+    /// children is cleared, so later appends can reuse the slots.
+    pub fn remove<K: Labels<L> + ?Sized>(&mut self, key: &K) -> Option<T> {
+        let mut state = self.walk(key)?;
+        let leaf = self.terminal(state)?;
+        let tail_key = self.base[leaf];
+        let value = self.tail.remove(&tail_key)?;
+        self.tail_free.push(tail_key);
+        self.release(leaf);
+
+        while state != 0 && self.children(state).is_empty() {
+            let parent = self.check[state] as usize;
+            self.unlink(parent, state - self.child_base(parent));
+            self.release(state);
+            state = parent;
+        }
+
+        Some(value)
     }
 
-    pub fn load(&mut self, list: Vec<(&str, T)>) {
+    pub fn load<K: Labels<L> + ?Sized>(&mut self, list: Vec<(&K, T)>) {
         for (key, value) in list {
             self.append(key, value);
         }
     }
 
-    pub fn search<'a>(&self, key: &str) -> Vec<(String, T)> {
-        let mut state = 0;
-        let mut results = Vec::new();
-        let mut current_key = String::new();
+    /// Returns every key that is a prefix of `key`, shortest first. Keys are
+    /// spelled as in `key`, even when a normalizer or equivalent labels made
+    /// them match a different spelling.
+    pub fn search<K: Labels<L> + ?Sized>(&self, key: &K) -> Vec<(L::Key, &T)> {
+        self.search_prefixes(key)
+    }
 
-        for ch in key.chars() {
-            current_key.push(ch);
+    /// Finds the longest key that is a prefix of `text` and returns its
+    /// length, the number of labels of `text` it covers and its value. For
+    /// `str` the length is in bytes and always falls on a char boundary, so
+    /// `&text[..len]` is the matched key and the count is in chars; for
+    /// slices both are in labels.
+    pub fn longest_prefix<K: Labels<L> + ?Sized>(&self, text: &K) -> Option<(usize, usize, &T)> {
+        self.longest_match(text.labels())
+    }
 
-            if let Some(&ch_code) = self.code_map.get(&ch) {
-                let new_state = (self.base[state].abs() as usize) + ch_code;
-                if new_state >= self.base.len() || self.check[new_state] != state as i32 {
-                    break;
-                }
-                state = new_state;
-            } else {
-                break;
-            }
+    pub fn lookup<K: Labels<L> + ?Sized>(&self, key: &K) -> Option<&T> {
+        self.value(self.walk(key)?)
+    }
 
-            if self.base[state] < 0 {
-                if let Some(value) = self.tail.get(&self.base[state]) {
-                    results.push((current_key.clone(), *value));
-                }
-            }
-        }
+    pub fn get_mut<K: Labels<L> + ?Sized>(&mut self, key: &K) -> Option<&mut T> {
+        let leaf = self.terminal(self.walk(key)?)?;
+        self.tail.get_mut(&self.base[leaf])
+    }
 
-        results
+    pub fn contain<K: Labels<L> + ?Sized>(&self, key: &K) -> bool {
+        self.lookup(key).is_some()
     }
+}
 
-    pub fn lookup(&self, key: &str) -> Option<T> {
-        let mut state = 0;
+impl<T, L: Label> DoubleArray<L> for Dat<T, L> {
+    type Value<'a>
+        = &'a T
+    where
+        Self: 'a;
 
-        for ch in key.chars() {
-            if let Some(&ch_code) = self.code_map.get(&ch) {
-                let new_state = (self.base[state].abs() as usize) + ch_code;
-                if new_state >= self.base.len() || self.check[new_state] != state as i32 {
-                    return None;
-                }
-                state = new_state;
-            } else {
-                return None;
-            }
+    fn len(&self) -> usize {
+        self.base.len()
+    }
 
-            if self.base[state] < 0 && ch == key.chars().last().unwrap() {
-                if let Some(value) = self.tail.get(&self.base[state]) {
-                    return Some(*value);
-                }
-            }
-        }
+    fn base(&self, state: usize) -> i32 {
+        self.base[state]
+    }
 
-        None
+    fn check(&self, state: usize) -> i32 {
+        self.check[state]
     }
 
-    pub fn contain(&self, key: &str) -> bool {
-        self.lookup(key).is_some()
+    fn child(&self, state: usize) -> usize {
+        self.child[state]
+    }
+
+    fn sibling(&self, state: usize) -> usize {
+        self.sibling[state]
+    }
+
+    fn tail_value(&self, tail_key: i32) -> Option<&T> {
+        self.tail.get(&tail_key)
+    }
+
+    fn normalizer(&self) -> Option<Normalizer<L>> {
+        self.normalizer
+    }
+
+    fn map_code(&self, value: u32) -> Option<usize> {
+        self.code_map.get(value)
+    }
+
+    fn map_label(&self, code: usize) -> Option<u32> {
+        Some(self.labels.get(code - 1)?.into_u32())
+    }
+
+    fn labels_len(&self) -> usize {
+        self.labels.len()
+    }
+}
+
+impl<T: Copy, L: Label> Dat<T, L> {
+    /// Same as [`Dat::lookup`], returning the value by copy.
+    pub fn lookup_copied<K: Labels<L> + ?Sized>(&self, key: &K) -> Option<T> {
+        self.lookup(key).copied()
+    }
+
+    /// Same as [`Dat::search`], returning the values by copy.
+    pub fn search_copied<K: Labels<L> + ?Sized>(&self, key: &K) -> Vec<(L::Key, T)> {
+        self.search(key)
+            .into_iter()
+            .map(|(key, &value)| (key, value))
+            .collect()
     }
 }
 
@@ -182,7 +490,7 @@ mod tests {
         assert_eq!(dat.base.len(), 1);
         assert_eq!(dat.check.len(), 1);
         assert_eq!(dat.tail.len(), 0);
-        assert_eq!(dat.code_map.len(), 0);
+        assert_eq!(dat.labels.len(), 0);
     }
 
     #[test]
@@ -191,8 +499,8 @@ mod tests {
         dat.append("key1", 1);
         dat.append("key2", 2);
 
-        assert_eq!(dat.lookup("key1"), Some(1));
-        assert_eq!(dat.lookup("key2"), Some(2));
+        assert_eq!(dat.lookup("key1"), Some(&1));
+        assert_eq!(dat.lookup("key2"), Some(&2));
         assert_eq!(dat.lookup("key3"), None);
     }
 
@@ -204,9 +512,101 @@ mod tests {
         dat.append("key1", 2);
 
         let results = dat.search("key1");
-        assert!(results.contains(&("key".to_string(), 1)));
-        assert!(results.contains(&("key1".to_string(), 2)));
+        assert!(results.contains(&("key".to_string(), &1)));
+        assert!(results.contains(&("key1".to_string(), &2)));
         assert_eq!(results.len(), 2);
+        assert_eq!(dat.tail.len(), 2);
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut dat: Dat<String> = Dat::new();
+        assert_eq!(dat.insert("key", "a".to_string()), None);
+        assert_eq!(dat.insert("key1", "b".to_string()), None);
+        let len = dat.base.len();
+
+        assert_eq!(dat.insert("key", "c".to_string()), Some("a".to_string()));
+        assert_eq!(dat.lookup("key"), Some(&"c".to_string()));
+        assert_eq!(dat.lookup("key1"), Some(&"b".to_string()));
+        assert_eq!(dat.tail.len(), 2);
+        assert_eq!(dat.base.len(), len);
+
+        dat.remove("key");
+        assert_eq!(dat.insert("key", "d".to_string()), None);
+        assert_eq!(dat.tail.len(), 2);
+        assert!(dat.tail_free.is_empty());
+    }
+
+    #[test]
+    fn test_longest_prefix_segments() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.load(vec![("我", 1), ("喜欢", 2), ("我喜欢", 3), ("你们", 4)]);
+
+        let text = "我喜欢你们a我";
+        let mut chars: Vec<char> = text.chars().collect();
+        let mut words = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            match dat.longest_prefix(&text[start..]) {
+                Some((len, count, _)) => {
+                    words.push(chars.drain(..count).collect::<String>());
+                    start += len;
+                }
+                None => {
+                    let ch = chars.remove(0);
+                    words.push(ch.to_string());
+                    start += ch.len_utf8();
+                }
+            }
+        }
+        assert_eq!(words, vec!["我喜欢", "你们", "a", "我"]);
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.load(vec![("我", 1), ("我喜欢", 2), ("喜欢", 3)]);
+
+        assert_eq!(
+            dat.longest_prefix("我喜欢你"),
+            Some(("我喜欢".len(), 3, &2))
+        );
+        assert_eq!(dat.longest_prefix("我喜"), Some(("我".len(), 1, &1)));
+        assert_eq!(dat.longest_prefix("喜欢"), Some((6, 2, &3)));
+        assert_eq!(dat.longest_prefix("你"), None);
+        assert_eq!(dat.longest_prefix(""), None);
+    }
+
+    #[test]
+    fn test_owned_values() {
+        let mut dat: Dat<Vec<&str>> = Dat::new();
+        dat.append("喜欢", vec!["v"]);
+        dat.append("喜", vec!["v", "n"]);
+
+        dat.get_mut("喜欢").unwrap().push("n");
+        assert_eq!(dat.lookup("喜欢"), Some(&vec!["v", "n"]));
+        assert_eq!(dat.get_mut("欢"), None);
+        assert_eq!(
+            dat.search("喜欢你"),
+            vec![
+                ("喜".to_string(), &vec!["v", "n"]),
+                ("喜欢".to_string(), &vec!["v", "n"])
+            ]
+        );
+        assert_eq!(dat.remove("喜"), Some(vec!["v", "n"]));
+    }
+
+    #[test]
+    fn test_copied() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.load(vec![("key", 1), ("key1", 2)]);
+
+        assert_eq!(dat.lookup_copied("key1"), Some(2));
+        assert_eq!(dat.lookup_copied("key2"), None);
+        assert_eq!(
+            dat.search_copied("key1"),
+            vec![("key".to_string(), 1), ("key1".to_string(), 2)]
+        );
     }
 
     #[test]
@@ -224,7 +624,373 @@ mod tests {
         let list = vec![("key1", 1), ("key2", 2)];
         dat.load(list);
 
-        assert_eq!(dat.lookup("key1"), Some(1));
-        assert_eq!(dat.lookup("key2"), Some(2));
+        assert_eq!(dat.lookup("key1"), Some(&1));
+        assert_eq!(dat.lookup("key2"), Some(&2));
+    }
+
+    #[test]
+    fn test_relocate() {
+        let mut dat: Dat<usize> = Dat::new();
+        let keys: Vec<String> = (0..512)
+            .map(|i| {
+                [i % 8, i / 8 % 8, i / 64]
+                    .iter()
+                    .map(|&c| char::from(b'a' + c as u8))
+                    .collect()
+            })
+            .collect();
+        for (i, key) in keys.iter().enumerate() {
+            dat.append(key, i);
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(dat.lookup(key), Some(&i));
+        }
+        for state in 1..dat.base.len() {
+            if !dat.is_vacant(state) {
+                let parent = dat.check[state] as usize;
+                assert!(dat
+                    .children(parent)
+                    .contains(&(state - dat.child_base(parent))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_after_branch() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.append("我喜欢", 1);
+        dat.append("我", 2);
+
+        assert_eq!(dat.lookup("我"), Some(&2));
+        assert_eq!(dat.lookup("我喜欢"), Some(&1));
+        assert_eq!(dat.lookup("我喜"), None);
+    }
+
+    #[test]
+    fn test_free_list() {
+        let mut dat: Dat<usize> = Dat::new();
+        let keys: Vec<String> = (0..1000).map(|i| format!("{:x}", i * 7919)).collect();
+        for (i, key) in keys.iter().enumerate() {
+            dat.append(key, i);
+        }
+        let len = dat.base.len();
+
+        for key in keys.iter().step_by(2) {
+            dat.remove(key);
+        }
+        for (i, key) in keys.iter().enumerate().step_by(2) {
+            dat.append(key, i);
+        }
+
+        assert!(dat.base.len() <= len + 16);
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(dat.lookup(key), Some(&i));
+        }
+        let mut state = dat.free_head;
+        while state != 0 {
+            assert!(dat.is_vacant(state));
+            state = -dat.check[state] as usize;
+            if state == dat.free_head {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.load(vec![("key1", 1), ("key2", 2), ("我喜欢你", 3)]);
+
+        assert_eq!(dat.remove("key1"), Some(1));
+        assert_eq!(dat.remove("key1"), None);
+        assert_eq!(dat.remove("key"), None);
+        assert_eq!(dat.remove("missing"), None);
+        assert_eq!(dat.lookup("key1"), None);
+        assert_eq!(dat.lookup("key2"), Some(&2));
+
+        assert_eq!(dat.remove("我喜欢你"), Some(3));
+        assert!(dat.search("我喜欢你").is_empty());
+        assert_eq!(dat.tail.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_prunes_branch() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.append("abc", 1);
+        let occupied = |dat: &Dat<i32>| (0..dat.base.len()).filter(|&i| !dat.is_vacant(i)).count();
+        let before = occupied(&dat);
+
+        dat.append("abd", 2);
+        assert_eq!(dat.remove("abd"), Some(2));
+        assert_eq!(occupied(&dat), before);
+        assert_eq!(dat.lookup("abc"), Some(&1));
+
+        assert_eq!(dat.remove("abc"), Some(1));
+        assert_eq!(occupied(&dat), 1);
+    }
+
+    #[test]
+    fn test_remove_reuses_space() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.append("abc", 1);
+        let len = dat.base.len();
+        dat.remove("abc");
+        assert_eq!(dat.tail_free.len(), 1);
+
+        dat.append("abc", 2);
+        assert_eq!(dat.base.len(), len);
+        assert_eq!(dat.tail_free.len(), 0);
+        assert_eq!(dat.tail.len(), 1);
+        assert_eq!(dat.lookup("abc"), Some(&2));
+    }
+
+    #[test]
+    fn test_remove_keeps_descendants() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.append("a", 1);
+        dat.append("ab", 2);
+
+        assert_eq!(dat.remove("a"), Some(1));
+        assert_eq!(dat.lookup("a"), None);
+        assert_eq!(dat.lookup("ab"), Some(&2));
+        assert_eq!(dat.search("ab"), vec![("ab".to_string(), &2)]);
+    }
+
+    #[test]
+    fn test_empty_key() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.load(vec![("a", 1), ("ab", 2)]);
+        assert_eq!(dat.lookup(""), None);
+        assert_eq!(dat.insert("", 0), None);
+        assert_eq!(dat.insert("", 3), Some(0));
+
+        assert_eq!(dat.lookup(""), Some(&3));
+        assert_eq!(dat.lookup("a"), Some(&1));
+        assert_eq!(dat.longest_prefix("b"), Some((0, 0, &3)));
+        assert_eq!(
+            dat.search("ab"),
+            vec![
+                ("".to_string(), &3),
+                ("a".to_string(), &1),
+                ("ab".to_string(), &2)
+            ]
+        );
+        assert_eq!(dat.keys().collect::<Vec<_>>(), vec!["", "a", "ab"]);
+
+        dat.append("b", 4);
+        assert_eq!(dat.remove(""), Some(3));
+        assert_eq!(dat.lookup(""), None);
+        assert_eq!(dat.keys().collect::<Vec<_>>(), vec!["a", "ab", "b"]);
+        assert_eq!(dat.search("ab").len(), 2);
+        assert_eq!(dat.remove(""), None);
+    }
+
+    #[test]
+    fn test_int_labels() {
+        let mut bytes: Dat<i32, u8> = Dat::new();
+        bytes.load(vec![(b"ab".as_slice(), 1), (b"abc", 2), (&[0xff], 3)]);
+        assert_eq!(bytes.lookup(b"abc"), Some(&2));
+        assert_eq!(bytes.lookup(&[0xffu8]), Some(&3));
+        assert_eq!(bytes.longest_prefix(b"abcd"), Some((3, 3, &2)));
+        assert_eq!(
+            bytes.keys().collect::<Vec<_>>(),
+            vec![b"ab".to_vec(), b"abc".to_vec(), vec![0xff]]
+        );
+
+        let mut tokens: Dat<&str, u32> = Dat::new();
+        tokens.insert(&[70_000, 1], "a");
+        tokens.insert(&vec![70_000], "b");
+        assert_eq!(
+            tokens.search(&[70_000, 1, 2]),
+            vec![(vec![70_000], &"b"), (vec![70_000, 1], &"a")]
+        );
+        assert_eq!(tokens.remove(&[70_000u32][..]), Some("b"));
+        assert_eq!(tokens.predict(&[70_000]).count(), 1);
+    }
+
+    #[test]
+    fn test_byte_labels() {
+        let words = ["我", "我喜欢", "喜欢", "ab"];
+        let mut bytes: Dat<usize, u8> = Dat::new();
+        let mut chars: Dat<usize> = Dat::new();
+        for (i, word) in words.iter().enumerate() {
+            bytes.append(word.as_bytes(), i);
+            chars.append(word, i);
+        }
+
+        assert!(bytes.labels.is_empty());
+        for text in ["我喜欢你", "喜欢", "我喜", "abc", "你"] {
+            assert_eq!(
+                bytes
+                    .longest_prefix(text.as_bytes())
+                    .map(|(len, _, value)| (len, value)),
+                chars
+                    .longest_prefix(text)
+                    .map(|(len, _, value)| (len, value))
+            );
+            assert_eq!(
+                bytes.search(text.as_bytes()).len(),
+                chars.search(text).len()
+            );
+        }
+        let keys: Vec<String> = bytes
+            .keys()
+            .map(|key| String::from_utf8(key).unwrap())
+            .collect();
+        assert_eq!(keys, chars.keys().collect::<Vec<_>>());
+        assert_eq!(bytes.remove("我".as_bytes()), Some(0));
+        assert_eq!(bytes.lookup("我喜欢".as_bytes()), Some(&1));
+    }
+
+    #[test]
+    fn test_char_slice_keys() {
+        let mut dat: Dat<i32> = Dat::new();
+        dat.append("我喜欢", 1);
+
+        assert_eq!(dat.lookup(&['我', '喜', '欢']), Some(&1));
+        assert_eq!(dat.lookup(&"我喜欢".to_string()), Some(&1));
+        assert_eq!(
+            dat.longest_prefix(&['我', '喜', '欢', '你']),
+            Some((3, 3, &1))
+        );
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let mut dat = Dat::with_normalizer(normalize::fold);
+        dat.append("ABC", 1);
+        dat.append("ａｂ", 2);
+        assert_eq!(dat.insert("abc", 3), Some(1));
+
+        assert_eq!(dat.lookup("ＡＢＣ"), Some(&3));
+        assert_eq!(dat.lookup("Ab"), Some(&2));
+        assert_eq!(dat.keys().collect::<Vec<_>>(), vec!["ab", "abc"]);
+        assert_eq!(
+            dat.search("ａＢcd"),
+            vec![("ａＢ".to_string(), &2), ("ａＢc".to_string(), &3)]
+        );
+        assert_eq!(dat.longest_prefix("ＡＢＣＤ"), Some((9, 3, &3)));
+        assert_eq!(dat.predict("A").count(), 2);
+        *dat.entry("ＡB").or_insert(0) += 10;
+        assert_eq!(dat.lookup("ab"), Some(&12));
+        assert_eq!(dat.remove("AB"), Some(12));
+        assert!(!dat.contain("ab"));
+    }
+
+    #[test]
+    fn test_normalizer_whole_chars() {
+        let mut dat = Dat::with_normalizer(normalize::lowercase);
+        dat.append("i", 1);
+        dat.append("i\u{307}x", 2);
+
+        assert_eq!(dat.longest_prefix("İ"), None);
+        assert_eq!(dat.longest_prefix("İX"), Some(("İX".len(), 2, &2)));
+        assert!(dat.search("İ").is_empty());
+    }
+
+    #[test]
+    fn test_equivalents() {
+        let mut dat = Dat::with_equivalents([['a', 'а'], ['书', '書']]);
+        dat.load(vec![("bad", 1), ("读书", 2)]);
+
+        assert_eq!(dat.lookup("bаd"), Some(&1));
+        assert_eq!(dat.lookup("讀書"), None);
+        assert_eq!(dat.lookup("读書"), Some(&2));
+        assert_eq!(dat.search("读書"), vec![("读書".to_string(), &2)]);
+        assert_eq!(dat.keys().collect::<Vec<_>>(), vec!["bad", "读书"]);
+        assert_eq!(dat.censor("so bаd", '*').1, vec![(3, 7, &1)]);
+
+        assert!(dat.add_equivalent('讀', '读'));
+        assert!(dat.add_equivalent('讀', '读'));
+        assert!(!dat.add_equivalent('b', 'd'));
+        let labels = dat.labels.len();
+        assert!(!dat.add_equivalent('b', 'z'));
+        assert_eq!(dat.labels.len(), labels);
+        assert_eq!(dat.lookup("讀書"), Some(&2));
+        assert_eq!(
+            dat.equivalents().collect::<HashMap<_, _>>(),
+            HashMap::from([('а', 'a'), ('書', '书'), ('讀', '读')])
+        );
+
+        let mut bytes: Dat<i32, u8> = Dat::new();
+        assert!(!bytes.add_equivalent(b'A', b'a'));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_equivalents_overlapping() {
+        Dat::<i32>::with_equivalents(["ab".chars(), "cb".chars()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "fixed alphabet")]
+    fn test_equivalents_fixed_alphabet() {
+        Dat::<i32, u8>::with_equivalents([*b"Aa"]);
+    }
+
+    fn permutations(keys: &[&'static str]) -> Vec<Vec<&'static str>> {
+        if keys.is_empty() {
+            return vec![vec![]];
+        }
+        let mut result = Vec::new();
+        for i in 0..keys.len() {
+            let mut rest = keys.to_vec();
+            let key = rest.remove(i);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, key);
+                result.push(permutation);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_nested_keys_every_order() {
+        let keys = ["我", "我喜", "我喜欢", "我喜欢你", "喜欢", "我们"];
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+
+        for order in permutations(&keys) {
+            let mut dat = Dat::new();
+            for key in &order {
+                assert_eq!(dat.insert(key, key.len()), None);
+            }
+            for key in &keys {
+                assert_eq!(dat.lookup(key), Some(&key.len()), "{:?}", order);
+            }
+            assert_eq!(dat.lookup("我喜欢你们"), None);
+            assert_eq!(dat.search("我喜欢你").len(), 4);
+            assert_eq!(dat.keys().collect::<Vec<_>>(), sorted, "{:?}", order);
+
+            for (i, key) in order.iter().enumerate() {
+                assert_eq!(dat.remove(key), Some(key.len()), "{:?}", order);
+                for rest in &order[i + 1..] {
+                    assert_eq!(dat.lookup(rest), Some(&rest.len()), "{:?}", order);
+                }
+            }
+            assert_eq!(dat.iter().count(), 0);
+            assert!(dat.tail.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_nested_keys_every_removal_order() {
+        let keys = ["a", "ab", "abc", "abd", "b"];
+        for insert in permutations(&keys) {
+            for remove in permutations(&keys) {
+                let mut dat = Dat::new();
+                for key in &insert {
+                    dat.append(key, key.to_string());
+                }
+                for (i, key) in remove.iter().enumerate() {
+                    assert_eq!(dat.remove(key).as_deref(), Some(*key));
+                    assert_eq!(dat.keys().count(), keys.len() - i - 1);
+                    for rest in &remove[i + 1..] {
+                        assert_eq!(dat.lookup(rest).map(String::as_str), Some(*rest));
+                    }
+                }
+            }
+        }
     }
 }
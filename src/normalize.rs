@@ -0,0 +1,174 @@
+//! Key normalizers for [`Dat::with_normalizer`](crate::Dat::with_normalizer).
+//!
+//! A normalizer maps one label of a key to the labels it is stored and
+//! matched as, so matches can always be traced back to whole labels of the
+//! original input. Folding that needs context across labels, such as
+//! composing a letter with a following combining mark, is not possible.
+
+use crate::Label;
+
+/// Appends the normalized form of a label to the buffer.
+pub type Normalizer<L> = fn(L, &mut Vec<L>);
+
+pub fn lowercase(ch: char, out: &mut Vec<char>) {
+    out.extend(ch.to_lowercase());
+}
+
+/// Folds full-width ASCII variants (U+FF01..U+FF5E) and the ideographic
+/// space to their half-width forms.
+pub fn half_width(ch: char, out: &mut Vec<char>) {
+    out.push(half_width_char(ch));
+}
+
+fn half_width_char(ch: char) -> char {
+    match ch {
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(ch as u32 - 0xfee0).unwrap(),
+        '\u{3000}' => ' ',
+        _ => ch,
+    }
+}
+
+/// [`half_width`] followed by [`lowercase`], so "ＡＢＣ" matches "abc".
+pub fn fold(ch: char, out: &mut Vec<char>) {
+    lowercase(half_width_char(ch), out);
+}
+
+/// Applies NFKC to each char on its own.
+#[cfg(feature = "unicode-normalization")]
+pub fn nfkc(ch: char, out: &mut Vec<char>) {
+    use unicode_normalization::UnicodeNormalization;
+
+    out.extend(std::iter::once(ch).nfkc());
+}
+
+/// [`nfkc`] followed by [`lowercase`].
+#[cfg(feature = "unicode-normalization")]
+pub fn nfkc_lowercase(ch: char, out: &mut Vec<char>) {
+    use unicode_normalization::UnicodeNormalization;
+
+    for ch in std::iter::once(ch).nfkc() {
+        lowercase(ch, out);
+    }
+}
+
+/// Whitespace, punctuation and invisible format chars, the usual filler
+/// inserted into words to get past a filter. Meant for
+/// [`Dat::set_ignorable`](crate::Dat::set_ignorable).
+pub fn noise(ch: char) -> bool {
+    ch.is_whitespace()
+        || ch.is_ascii_punctuation()
+        || matches!(
+            ch,
+            '\u{ad}'
+                | '\u{180e}'
+                | '\u{200b}'..='\u{200f}'
+                | '\u{2010}'..='\u{2027}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{3001}'..='\u{3003}'
+                | '\u{3008}'..='\u{3011}'
+                | '\u{30fb}'
+                | '\u{feff}'
+                | '\u{ff01}'..='\u{ff0f}'
+                | '\u{ff1a}'..='\u{ff20}'
+                | '\u{ff3b}'..='\u{ff40}'
+                | '\u{ff5b}'..='\u{ff65}'
+        )
+}
+
+/// Labels of a key after normalization. Each comes with the end offset of
+/// the original label it was produced from, but only if it is the last one
+/// produced from it; matches ending elsewhere would split an input label.
+pub(crate) struct Normalized<I, L> {
+    labels: I,
+    normalizer: Option<Normalizer<L>>,
+    buffer: Vec<L>,
+    next: usize,
+    end: usize,
+}
+
+impl<I, L> Normalized<I, L> {
+    pub(crate) fn new(labels: I, normalizer: Option<Normalizer<L>>) -> Self {
+        Self {
+            labels,
+            normalizer,
+            buffer: Vec::new(),
+            next: 0,
+            end: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = (usize, L)>, L: Label> Iterator for Normalized<I, L> {
+    type Item = (Option<usize>, L);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(&label) = self.buffer.get(self.next) {
+                self.next += 1;
+                let end = (self.next == self.buffer.len()).then_some(self.end);
+                return Some((end, label));
+            }
+
+            let (end, label) = self.labels.next()?;
+            let Some(normalizer) = self.normalizer else {
+                return Some((Some(end), label));
+            };
+            self.buffer.clear();
+            normalizer(label, &mut self.buffer);
+            self.next = 0;
+            self.end = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Labels;
+
+    fn apply(normalizer: Normalizer<char>, text: &str) -> String {
+        let mut out = Vec::new();
+        for ch in text.chars() {
+            normalizer(ch, &mut out);
+        }
+        out.into_iter().collect()
+    }
+
+    #[test]
+    fn test_normalizers() {
+        assert_eq!(apply(lowercase, "ABC Straße İ"), "abc straße i\u{307}");
+        assert_eq!(apply(half_width, "ＡＢＣ！　１"), "ABC! 1");
+        assert_eq!(apply(fold, "ＡＢＣ你"), "abc你");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_nfkc() {
+        assert_eq!(apply(nfkc, "ＡＢＣ①ﬁ"), "ABC1fi");
+        assert_eq!(apply(nfkc_lowercase, "ＡＢＣ㍿"), "abc株式会社");
+    }
+
+    #[test]
+    fn test_noise() {
+        let kept: String = "你 不，配\u{200b}！a-b_c。\u{feff}"
+            .chars()
+            .filter(|&ch| !noise(ch))
+            .collect();
+        assert_eq!(kept, "你不配abc");
+    }
+
+    #[test]
+    fn test_normalized_offsets() {
+        let items: Vec<_> =
+            Normalized::new("Aİb".labels(), Some(lowercase as Normalizer<char>)).collect();
+        assert_eq!(
+            items,
+            vec![
+                (Some(1), 'a'),
+                (None, 'i'),
+                (Some(3), '\u{307}'),
+                (Some(4), 'b')
+            ]
+        );
+    }
+}
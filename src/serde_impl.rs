@@ -0,0 +1,159 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::code_map::CodeMap;
+use crate::{Dat, Label};
+
+#[derive(Serialize)]
+struct DatRef<'a, T, L> {
+    base: &'a [i32],
+    check: &'a [i32],
+    child: &'a [usize],
+    sibling: &'a [usize],
+    free_head: usize,
+    labels: &'a [L],
+    equivalents: Vec<(L, L)>,
+    tail_free: &'a [i32],
+    tail: Vec<(i32, &'a T)>,
+}
+
+#[derive(Deserialize)]
+struct DatData<T, L> {
+    base: Vec<i32>,
+    check: Vec<i32>,
+    child: Vec<usize>,
+    sibling: Vec<usize>,
+    free_head: usize,
+    labels: Vec<L>,
+    #[serde(default = "Vec::new")]
+    equivalents: Vec<(L, L)>,
+    tail_free: Vec<i32>,
+    tail: Vec<(i32, T)>,
+}
+
+impl<T: Serialize, L: Label + Serialize> Serialize for Dat<T, L> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tail: Vec<(i32, &T)> = self.tail.iter().map(|(&key, value)| (key, value)).collect();
+        tail.sort_unstable_by_key(|&(key, _)| key);
+        let mut equivalents: Vec<(L, L)> = self.equivalents().collect();
+        equivalents.sort_unstable();
+
+        DatRef {
+            base: &self.base,
+            check: &self.check,
+            child: &self.child,
+            sibling: &self.sibling,
+            free_head: self.free_head,
+            labels: &self.labels,
+            equivalents,
+            tail_free: &self.tail_free,
+            tail,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, L: Label + Deserialize<'de>> Deserialize<'de> for Dat<T, L> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data: DatData<T, L> = DatData::deserialize(deserializer)?;
+        let len = data.base.len();
+        if data.check.len() != len || data.child.len() != len || data.sibling.len() != len {
+            return Err(D::Error::custom("mismatched array lengths"));
+        }
+
+        let mut dat = Dat {
+            base: data.base,
+            check: data.check,
+            child: data.child,
+            sibling: data.sibling,
+            tail: data.tail.into_iter().collect(),
+            tail_free: data.tail_free,
+            free_head: data.free_head,
+            code_map: CodeMap::from_labels(data.labels.iter().map(|label| label.into_u32())),
+            labels: data.labels,
+            normalizer: None,
+            ignorable: None,
+        };
+        let labels = dat.labels.len();
+        for (alias, label) in data.equivalents {
+            if !dat.add_equivalent(alias, label) || dat.labels.len() != labels {
+                return Err(D::Error::custom("invalid equivalent"));
+            }
+        }
+        if dat.is_valid() {
+            Ok(dat)
+        } else {
+            Err(D::Error::custom("corrupted datrie data"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dat;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let mut dat = Dat::new();
+        dat.load(vec![
+            ("我", 1),
+            ("我喜欢", 2),
+            ("我喜欢你", 3),
+            ("你不配", 4),
+            ("ключ", 5),
+        ]);
+        dat.remove("我喜欢");
+
+        let json = serde_json::to_string(&dat).unwrap();
+        let mut loaded: Dat<i32> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.code_map, dat.code_map);
+        assert_eq!(loaded.lookup("ключ"), Some(&5));
+        assert_eq!(loaded.lookup("我喜欢"), None);
+
+        loaded.append("我喜欢", 6);
+        assert_eq!(loaded.search("我喜欢你").len(), 3);
+    }
+
+    #[test]
+    fn test_serde_equivalents() {
+        let mut dat = Dat::with_equivalents([['a', 'а']]);
+        dat.append("ab", 1);
+
+        let json = serde_json::to_string(&dat).unwrap();
+        let loaded: Dat<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.code_map, dat.code_map);
+        assert_eq!(loaded.lookup("аb"), Some(&1));
+
+        let mut value = serde_json::to_value(&dat).unwrap();
+        value["equivalents"] = serde_json::json!([["а", "x"]]);
+        assert!(serde_json::from_value::<Dat<i32>>(value).is_err());
+    }
+
+    #[test]
+    fn test_serde_rejects_corrupted() {
+        let mut dat = Dat::new();
+        dat.append("abc", 1);
+        let mut value = serde_json::to_value(&dat).unwrap();
+        value["check"][1] = serde_json::json!(100);
+
+        assert!(serde_json::from_value::<Dat<i32>>(value).is_err());
+    }
+
+    #[test]
+    fn test_serde_rejects_corrupted_free_list() {
+        let mut dat = Dat::new();
+        dat.load(vec![("abc", 1), ("abd", 2)]);
+        dat.remove("abd");
+        let head = dat.free_head;
+        assert_ne!(-dat.check[head] as usize, head);
+        let mut value = serde_json::to_value(&dat).unwrap();
+        value["check"][head] = serde_json::json!(-(head as i32));
+
+        assert!(serde_json::from_value::<Dat<i32>>(value).is_err());
+    }
+}
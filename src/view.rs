@@ -0,0 +1,444 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use std::marker::PhantomData;
+
+use crate::binary::{invalid, MAGIC, VERSION};
+use crate::code_map::{CodeMap, BLOCK, DENSE_LIMIT};
+use crate::double_array::DoubleArray;
+use crate::{Codec, Dat, Label, Labels, Normalizer};
+
+/// Read-only trie borrowing the output of [`Dat::write_to`], e.g. from a
+/// memory-mapped file.
+///
+/// Queries read `base`, `check` and the tail straight from the buffer and
+/// only decode the values they return, so opening a view costs nothing
+/// beyond checking the section sizes. The contents are trusted: a corrupted
+/// buffer gives wrong answers rather than errors.
+pub struct DatView<'a, T, L = char> {
+    len: usize,
+    base: &'a [u8],
+    check: &'a [u8],
+    child: &'a [u8],
+    sibling: &'a [u8],
+    free_head: usize,
+    labels: &'a [u8],
+    code_index: &'a [u8],
+    code_table: &'a [u8],
+    code_fallback: &'a [u8],
+    tail_free: &'a [u8],
+    table: &'a [u8],
+    values: &'a [u8],
+    normalizer: Option<Normalizer<L>>,
+    marker: PhantomData<fn() -> (T, L)>,
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn take_array(&mut self, len: usize, width: usize) -> io::Result<&'a [u8]> {
+        self.take(
+            len.checked_mul(width)
+                .ok_or_else(|| invalid("section too large"))?,
+        )
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        usize::decode(&mut self.take(8)?)
+    }
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+fn read_i32(bytes: &[u8], index: usize) -> i32 {
+    read_u32(bytes, index) as i32
+}
+
+impl<'a, T: Codec, L: Label> DatView<'a, T, L> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        let mut cursor = Cursor { bytes };
+        if cursor.take(4)? != MAGIC {
+            return Err(invalid("not a datrie file"));
+        }
+        if u32::decode(&mut cursor.take(4)?)? != VERSION {
+            return Err(invalid("unsupported datrie version"));
+        }
+
+        let len = cursor.len()?;
+        let base = cursor.take_array(len, 4)?;
+        let check = cursor.take_array(len, 4)?;
+        let child = cursor.take_array(len, 4)?;
+        let sibling = cursor.take_array(len, 4)?;
+        let free_head = cursor.len()?;
+        let labels_len = cursor.len()?;
+        let labels = cursor.take_array(labels_len, 4)?;
+        let code_index_len = cursor.len()?;
+        let code_index = cursor.take_array(code_index_len, 4)?;
+        let code_table_len = cursor.len()?;
+        let code_table = cursor.take_array(code_table_len, 4)?;
+        let code_fallback_len = cursor.len()?;
+        let code_fallback = cursor.take_array(code_fallback_len, 8)?;
+        let tail_free_len = cursor.len()?;
+        let tail_free = cursor.take_array(tail_free_len, 4)?;
+        let tail_len = cursor.len()?;
+        let table = cursor.take_array(tail_len, 12)?;
+        let values_len = cursor.len()?;
+        let values = cursor.take(values_len)?;
+
+        if len == 0 {
+            return Err(invalid("corrupted datrie data"));
+        }
+
+        Ok(Self {
+            len,
+            base,
+            check,
+            child,
+            sibling,
+            free_head,
+            labels,
+            code_index,
+            code_table,
+            code_fallback,
+            tail_free,
+            table,
+            values,
+            normalizer: None,
+            marker: PhantomData,
+        })
+    }
+
+    /// Same as [`Dat::set_normalizer`]; it must match the one the trie was
+    /// built with.
+    pub fn with_normalizer(mut self, normalizer: Normalizer<L>) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    pub fn lookup<K: Labels<L> + ?Sized>(&self, key: &K) -> Option<T> {
+        self.value(self.walk(key)?)
+    }
+
+    pub fn contain<K: Labels<L> + ?Sized>(&self, key: &K) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    /// Same as [`Dat::search`].
+    pub fn search<K: Labels<L> + ?Sized>(&self, key: &K) -> Vec<(L::Key, T)> {
+        self.search_prefixes(key)
+    }
+
+    /// Same as [`Dat::longest_prefix`].
+    pub fn longest_prefix<K: Labels<L> + ?Sized>(&self, text: &K) -> Option<(usize, usize, T)> {
+        self.longest_match(text.labels())
+    }
+
+    /// Same as [`Dat::predict`], in Unicode order.
+    pub fn predict<K: Labels<L> + ?Sized>(
+        &self,
+        prefix: &K,
+    ) -> impl Iterator<Item = (L::Key, T)> + '_ {
+        let mut stack = self.predict_stack(prefix);
+        std::iter::from_fn(move || self.predict_next(&mut stack))
+    }
+}
+
+impl<T: Codec, L: Label> DoubleArray<L> for DatView<'_, T, L> {
+    type Value<'b>
+        = T
+    where
+        Self: 'b;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn base(&self, state: usize) -> i32 {
+        read_i32(self.base, state)
+    }
+
+    fn check(&self, state: usize) -> i32 {
+        read_i32(self.check, state)
+    }
+
+    fn child(&self, state: usize) -> usize {
+        read_u32(self.child, state) as usize
+    }
+
+    fn sibling(&self, state: usize) -> usize {
+        read_u32(self.sibling, state) as usize
+    }
+
+    fn tail_value(&self, tail_key: i32) -> Option<T> {
+        let (mut low, mut high) = (0, self.table.len() / 12);
+        while low < high {
+            let mid = (low + high) / 2;
+            let entry = &self.table[mid * 12..mid * 12 + 12];
+            match read_i32(entry, 0).cmp(&tail_key) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    let offset = usize::decode(&mut &entry[4..]).ok()?;
+                    return T::decode(&mut self.values.get(offset..)?).ok();
+                }
+            }
+        }
+        None
+    }
+
+    fn normalizer(&self) -> Option<Normalizer<L>> {
+        self.normalizer
+    }
+
+    fn map_code(&self, value: u32) -> Option<usize> {
+        if value < DENSE_LIMIT {
+            let high = value as usize / BLOCK;
+            if high >= self.code_index.len() / 4 {
+                return None;
+            }
+            let slot = read_u32(self.code_index, high) as usize * BLOCK + value as usize % BLOCK;
+            if slot >= self.code_table.len() / 4 {
+                return None;
+            }
+            let code = read_u32(self.code_table, slot) as usize;
+            return (code != 0).then_some(code);
+        }
+
+        let (mut low, mut high) = (0, self.code_fallback.len() / 8);
+        while low < high {
+            let mid = (low + high) / 2;
+            match read_u32(self.code_fallback, mid * 2).cmp(&value) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(read_u32(self.code_fallback, mid * 2 + 1) as usize),
+            }
+        }
+        None
+    }
+
+    fn map_label(&self, code: usize) -> Option<u32> {
+        (code <= self.labels.len() / 4).then(|| read_u32(self.labels, code - 1))
+    }
+
+    fn labels_len(&self) -> usize {
+        self.labels.len() / 4
+    }
+}
+
+impl<'a, T: Codec, L: Label> DatView<'a, T, L> {
+    /// Copies the buffer into an owned, mutable [`Dat`].
+    pub fn to_dat(&self) -> io::Result<Dat<T, L>> {
+        let words = |bytes: &[u8]| {
+            (0..bytes.len() / 4)
+                .map(|i| read_u32(bytes, i))
+                .collect::<Vec<_>>()
+        };
+
+        let labels = words(self.labels)
+            .into_iter()
+            .map(|label| L::from_u32(label).ok_or_else(|| invalid("invalid label")))
+            .collect::<io::Result<Vec<L>>>()?;
+        let mut code_map = CodeMap::default();
+        for high in 0..self.code_index.len() / 4 {
+            let block = read_u32(self.code_index, high) as usize;
+            if block == 0 {
+                continue;
+            }
+            for low in 0..BLOCK {
+                let slot = block * BLOCK + low;
+                if slot >= self.code_table.len() / 4 {
+                    return Err(invalid("invalid code table"));
+                }
+                let code = read_u32(self.code_table, slot) as usize;
+                if code != 0 {
+                    code_map.insert((high * BLOCK + low) as u32, code);
+                }
+            }
+        }
+        for pair in self.code_fallback.chunks(8) {
+            code_map.insert(read_u32(pair, 0), read_u32(pair, 1) as usize);
+        }
+
+        let mut tail = HashMap::new();
+        for entry in self.table.chunks(12) {
+            let offset = usize::decode(&mut &entry[4..])?;
+            let mut value = self
+                .values
+                .get(offset..)
+                .ok_or_else(|| invalid("invalid value offset"))?;
+            tail.insert(read_i32(entry, 0), T::decode(&mut value)?);
+        }
+
+        Ok(Dat {
+            base: words(self.base)
+                .into_iter()
+                .map(|base| base as i32)
+                .collect(),
+            check: words(self.check)
+                .into_iter()
+                .map(|check| check as i32)
+                .collect(),
+            child: words(self.child)
+                .into_iter()
+                .map(|code| code as usize)
+                .collect(),
+            sibling: words(self.sibling)
+                .into_iter()
+                .map(|code| code as usize)
+                .collect(),
+            tail,
+            tail_free: words(self.tail_free)
+                .into_iter()
+                .map(|key| key as i32)
+                .collect(),
+            free_head: self.free_head,
+            code_map,
+            labels,
+            normalizer: self.normalizer,
+            ignorable: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Dat<u32>, Vec<u8>) {
+        let mut dat = Dat::new();
+        dat.load(vec![
+            ("我", 1),
+            ("我喜欢", 2),
+            ("我喜欢你", 3),
+            ("你不配", 4),
+            ("ab", 5),
+            ("", 6),
+        ]);
+        let mut bytes = Vec::new();
+        dat.write_to(&mut bytes).unwrap();
+        (dat, bytes)
+    }
+
+    #[test]
+    fn test_view_queries() {
+        let (dat, bytes) = sample();
+        let view: DatView<u32> = DatView::new(&bytes).unwrap();
+
+        for key in [
+            "我",
+            "我喜欢",
+            "我喜欢你",
+            "你不配",
+            "ab",
+            "a",
+            "我喜",
+            "他",
+            "",
+        ] {
+            assert_eq!(view.lookup(key), dat.lookup_copied(key));
+            assert_eq!(view.search(key), dat.search_copied(key));
+            assert_eq!(
+                view.longest_prefix(key),
+                dat.longest_prefix(key)
+                    .map(|(len, count, &value)| (len, count, value))
+            );
+            assert_eq!(
+                view.predict(key).collect::<Vec<_>>(),
+                dat.predict(key)
+                    .map(|(key, &value)| (key, value))
+                    .collect::<Vec<_>>()
+            );
+        }
+        assert!(view.contain("你不配"));
+        assert!(!view.contain("你配"));
+    }
+
+    #[test]
+    fn test_view_large_labels() {
+        let mut dat: Dat<u8, u32> = Dat::new();
+        dat.load(vec![(&[5, 2_000_000][..], 1), (&[2_000_000], 2), (&[5], 3)]);
+        let mut bytes = Vec::new();
+        dat.write_to(&mut bytes).unwrap();
+        let view: DatView<u8, u32> = DatView::new(&bytes).unwrap();
+
+        assert_eq!(view.lookup(&[5, 2_000_000]), Some(1));
+        assert_eq!(view.lookup(&[2_000_000]), Some(2));
+        assert_eq!(view.lookup(&[2_000_001]), None);
+        assert_eq!(view.lookup(&[6]), None);
+        assert_eq!(
+            view.to_dat().unwrap().iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_view_equivalents() {
+        let mut dat = Dat::with_equivalents([['a', 'а'], ['我', '𝕒']]);
+        dat.load(vec![("ab", 1u32), ("我", 2)]);
+        let mut bytes = Vec::new();
+        dat.write_to(&mut bytes).unwrap();
+        let view = DatView::<u32>::new(&bytes).unwrap();
+
+        assert_eq!(view.lookup("аb"), Some(1));
+        assert_eq!(view.lookup("𝕒"), Some(2));
+        let mut owned = view.to_dat().unwrap();
+        assert_eq!(owned.code_map, dat.code_map);
+        owned.append("аа", 3);
+        assert_eq!(owned.lookup("aa"), Some(&3));
+    }
+
+    #[test]
+    fn test_view_normalizer() {
+        let mut dat = Dat::with_normalizer(crate::normalize::fold);
+        dat.load(vec![("ab", 1u8), ("abc", 2)]);
+        let mut bytes = Vec::new();
+        dat.write_to(&mut bytes).unwrap();
+        let view = DatView::<u8>::new(&bytes)
+            .unwrap()
+            .with_normalizer(crate::normalize::fold);
+
+        assert_eq!(view.lookup("ＡＢ"), Some(1));
+        assert_eq!(view.longest_prefix("ＡＢＣd"), Some((9, 3, 2)));
+        assert_eq!(
+            view.search("ａBc"),
+            vec![("ａB".to_string(), 1), ("ａBc".to_string(), 2)]
+        );
+        assert_eq!(view.to_dat().unwrap().lookup("ABC"), Some(&2));
+    }
+
+    #[test]
+    fn test_view_rejects_bad_header() {
+        let (_, bytes) = sample();
+
+        assert!(DatView::<u32>::new(&bytes[..bytes.len() - 1]).is_err());
+        assert!(DatView::<u32>::new(b"DATX").is_err());
+        assert!(DatView::<u32>::new(&[]).is_err());
+    }
+
+    #[test]
+    fn test_view_to_dat() {
+        let (dat, bytes) = sample();
+        let mut owned = DatView::<u32>::new(&bytes).unwrap().to_dat().unwrap();
+
+        assert_eq!(
+            owned.iter().collect::<Vec<_>>(),
+            dat.iter().collect::<Vec<_>>()
+        );
+        owned.append("我们", 7);
+        assert_eq!(owned.lookup("我们"), Some(&7));
+        assert_eq!(owned.lookup(""), Some(&6));
+    }
+}